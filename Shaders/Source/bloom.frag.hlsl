@@ -0,0 +1,28 @@
+Texture2D<float4> Texture : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float4 Params; // x: threshold, y: intensity, z: radius in screen pixels
+    float2 TexelSize;
+    float2 Padding;
+};
+
+float4 main(float2 TexCoord : TEXCOORD0) : SV_Target0 {
+    float4 color = Texture.Sample(Sampler, TexCoord);
+
+    // Gaussian-weighted sum of the bright parts of a 9x9 neighbourhood
+    float3 bloom = float3(0.0f, 0.0f, 0.0f);
+    float total = 0.0f;
+    for (int x = -4; x <= 4; x++) {
+        for (int y = -4; y <= 4; y++) {
+            float2 offset = float2(x, y) * TexelSize * (Params.z / 4.0f);
+            float3 value = Texture.Sample(Sampler, TexCoord + offset).rgb;
+            float brightness = max(value.r, max(value.g, value.b));
+            float weight = exp(-(x * x + y * y) / 8.0f);
+            bloom += value * saturate(brightness - Params.x) * weight;
+            total += weight;
+        }
+    }
+
+    return float4(color.rgb + (bloom / total) * Params.y, color.a);
+}
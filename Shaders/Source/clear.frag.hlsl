@@ -0,0 +1,16 @@
+cbuffer UniformBlock : register(b0, space3) {
+    float4 ClearColor : packoffset(c0);
+};
+
+struct Output {
+    float4 Color : SV_Target0;
+    float Depth  : SV_Depth;
+};
+
+// Fills a camera viewport inside the shared render pass, which can only clear all of it
+Output main(float2 TexCoord : TEXCOORD0) {
+    Output output;
+    output.Color = ClearColor;
+    output.Depth = 1.0f;
+    return output;
+}
@@ -0,0 +1,31 @@
+Texture2D<float4> Texture : register(t0, space2);
+Texture2D<float4> Lut : register(t1, space2);
+SamplerState Sampler : register(s0, space2);
+SamplerState LutSampler : register(s1, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float4 Params; // x: intensity, y: LUT size (a size^3 LUT laid out as size slices side by side)
+    float2 TexelSize;
+    float2 Padding;
+};
+
+float3 SampleLut(float3 color) {
+    float size = Params.y;
+    float blue = saturate(color.b) * (size - 1.0f);
+    float slice0 = floor(blue);
+    float slice1 = min(slice0 + 1.0f, size - 1.0f);
+
+    float2 texcoord = float2(
+        (saturate(color.r) * (size - 1.0f) + 0.5f) / (size * size),
+        (saturate(color.g) * (size - 1.0f) + 0.5f) / size
+    );
+
+    float3 a = Lut.Sample(LutSampler, texcoord + float2(slice0 / size, 0.0f)).rgb;
+    float3 b = Lut.Sample(LutSampler, texcoord + float2(slice1 / size, 0.0f)).rgb;
+    return lerp(a, b, blue - slice0);
+}
+
+float4 main(float2 TexCoord : TEXCOORD0) : SV_Target0 {
+    float4 color = Texture.Sample(Sampler, TexCoord);
+    return float4(lerp(color.rgb, SampleLut(color.rgb), Params.x), color.a);
+}
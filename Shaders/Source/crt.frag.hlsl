@@ -0,0 +1,23 @@
+Texture2D<float4> Texture : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float4 Params; // x: curvature, y: scanline intensity, z: scanline count
+    float2 TexelSize;
+    float2 Padding;
+};
+
+float4 main(float2 TexCoord : TEXCOORD0) : SV_Target0 {
+    // Barrel distortion around the screen centre
+    float2 centered = TexCoord * 2.0f - 1.0f;
+    centered *= 1.0f + Params.x * dot(centered, centered);
+    float2 texcoord = centered * 0.5f + 0.5f;
+
+    if (any(texcoord < 0.0f) || any(texcoord > 1.0f)) {
+        return float4(0.0f, 0.0f, 0.0f, 1.0f);
+    }
+
+    float4 color = Texture.Sample(Sampler, texcoord);
+    float scanline = sin(texcoord.y * Params.z * 3.14159265f) * 0.5f + 0.5f;
+    return float4(color.rgb * lerp(1.0f, scanline, Params.y), color.a);
+}
@@ -0,0 +1,14 @@
+struct Output {
+    float2 TexCoord : TEXCOORD0;
+    float4 Position : SV_Position;
+};
+
+// Single triangle covering the whole screen, no vertex buffer needed
+Output main(uint id : SV_VertexID) {
+    float2 texcoord = float2((id << 1) & 2, id & 2);
+
+    Output output;
+    output.TexCoord = texcoord;
+    output.Position = float4(texcoord * float2(2.0f, -2.0f) + float2(-1.0f, 1.0f), 0.0f, 1.0f);
+    return output;
+}
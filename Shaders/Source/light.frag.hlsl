@@ -0,0 +1,79 @@
+struct LightData {
+    float2 Position;
+    float Height;
+    float Radius;
+    float3 Color;
+    float Intensity;
+    float2 Direction;
+    float CosInner;
+    float CosOuter;
+};
+
+struct OccluderData {
+    float2 Min;
+    float2 Max;
+};
+
+Texture2D<float4> NormalBuffer : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+StructuredBuffer<LightData> LightBuffer : register(t1, space2);
+StructuredBuffer<OccluderData> OccluderBuffer : register(t2, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float2 ScreenSize;
+    uint OccluderCount;
+    float Padding;
+};
+
+struct Input {
+    float2 WorldPosition : TEXCOORD0;
+    nointerpolation uint LightIndex : TEXCOORD1;
+    float4 Position : SV_Position;
+};
+
+// Slab test of the segment from -> to against an axis-aligned box
+bool SegmentHitsBox(float2 from, float2 to, OccluderData box) {
+    float2 dir = to - from;
+    float2 invDir = 1.0f / dir;
+    float2 t0 = (box.Min - from) * invDir;
+    float2 t1 = (box.Max - from) * invDir;
+    float2 tMin = min(t0, t1);
+    float2 tMax = max(t0, t1);
+    float enter = max(tMin.x, tMin.y);
+    float exit = min(tMax.x, tMax.y);
+    return enter <= exit && exit >= 0.0f && enter <= 1.0f;
+}
+
+float4 main(Input input) : SV_Target0 {
+    LightData light = LightBuffer[input.LightIndex];
+
+    float2 toLight = light.Position - input.WorldPosition;
+    float dist = length(toLight);
+    float attenuation = saturate(1.0f - dist / light.Radius);
+    attenuation *= attenuation;
+
+    // Point lights use CosOuter = -1 so the cone never cuts them
+    if (light.CosOuter > -1.0f) {
+        float cosAngle = dot(-toLight / max(dist, 0.0001f), light.Direction);
+        attenuation *= smoothstep(light.CosOuter, light.CosInner, cosAngle);
+    }
+
+    if (attenuation <= 0.0f) {
+        discard;
+    }
+
+    // Pixels inside an occluder stay lit so the occluder itself is visible
+    for (uint i = 0; i < OccluderCount; i++) {
+        OccluderData box = OccluderBuffer[i];
+        bool inside = all(input.WorldPosition >= box.Min) && all(input.WorldPosition <= box.Max);
+        if (!inside && SegmentHitsBox(input.WorldPosition, light.Position, box)) {
+            discard;
+        }
+    }
+
+    float3 normal = normalize(NormalBuffer.Sample(Sampler, input.Position.xy / ScreenSize).rgb * 2.0f - 1.0f);
+    float3 lightDir = normalize(float3(toLight, light.Height));
+    float diffuse = saturate(dot(normal, lightDir));
+
+    return float4(light.Color * light.Intensity * attenuation * diffuse, 1.0f);
+}
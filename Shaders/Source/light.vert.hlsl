@@ -0,0 +1,41 @@
+struct LightData {
+    float2 Position;
+    float Height;
+    float Radius;
+    float3 Color;
+    float Intensity;
+    float2 Direction;
+    float CosInner;
+    float CosOuter;
+};
+
+struct Output {
+    float2 WorldPosition : TEXCOORD0;
+    nointerpolation uint LightIndex : TEXCOORD1;
+    float4 Position : SV_Position;
+};
+
+StructuredBuffer<LightData> LightBuffer : register(t0, space0);
+
+cbuffer UniformBlock : register(b0, space1) {
+    float4x4 ViewProjectionMatrix : packoffset(c0);
+};
+
+static const uint triangleIndices[6] = { 0, 1, 2, 3, 2, 1 };
+
+// One quad per light covering its radius
+Output main(uint id : SV_VertexID) {
+    uint lightIndex = id / 6;
+    uint vert       = triangleIndices[id % 6];
+
+    LightData light = LightBuffer[lightIndex];
+
+    float2 corner = float2((vert & 1), (vert >> 1)) * 2.0f - 1.0f;
+    float2 worldPos = light.Position + corner * light.Radius;
+
+    Output output;
+    output.WorldPosition = worldPos;
+    output.LightIndex    = lightIndex;
+    output.Position      = mul(ViewProjectionMatrix, float4(worldPos, 0.0f, 1.0f));
+    return output;
+}
@@ -0,0 +1,13 @@
+Texture2D<float4> LightMap : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float2 ScreenSize;
+    float2 Padding;
+};
+
+// Drawn with multiplicative blending over the scene. The light map is sampled by pixel position
+// so it stays aligned with the scene when a viewport is set.
+float4 main(float2 TexCoord : TEXCOORD0, float4 Position : SV_Position) : SV_Target0 {
+    return float4(LightMap.Sample(Sampler, Position.xy / ScreenSize).rgb, 1.0f);
+}
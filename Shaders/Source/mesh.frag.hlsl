@@ -0,0 +1,21 @@
+Texture2D<float4> Texture : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float4 BaseColor : packoffset(c0);
+    // xyz: direction the light travels in, w: ambient amount
+    float4 LightDirection : packoffset(c1);
+};
+
+struct Input {
+    float2 TexCoord : TEXCOORD0;
+    float3 Normal   : TEXCOORD1;
+};
+
+float4 main(Input input) : SV_Target0 {
+    float diffuse = saturate(dot(normalize(input.Normal), -normalize(LightDirection.xyz)));
+    float lighting = LightDirection.w + (1.0f - LightDirection.w) * diffuse;
+
+    float4 color = BaseColor * Texture.Sample(Sampler, input.TexCoord);
+    return float4(color.rgb * lighting, color.a);
+}
@@ -0,0 +1,27 @@
+struct Input {
+    float3 Position : TEXCOORD0;
+    float3 Normal   : TEXCOORD1;
+    float2 TexCoord : TEXCOORD2;
+};
+
+struct Output {
+    float2 TexCoord : TEXCOORD0;
+    float3 Normal   : TEXCOORD1;
+    float4 Position : SV_Position;
+};
+
+cbuffer UniformBlock : register(b0, space1) {
+    float4x4 ViewProjectionMatrix : packoffset(c0);
+    float4x4 ModelMatrix : packoffset(c4);
+};
+
+Output main(Input input) {
+    float4 worldPos = mul(ModelMatrix, float4(input.Position, 1.0f));
+
+    Output output;
+    output.Position = mul(ViewProjectionMatrix, worldPos);
+    output.TexCoord = input.TexCoord;
+    // Good enough for uniform scale, non-uniform scale would need the inverse transpose
+    output.Normal = normalize(mul((float3x3)ModelMatrix, input.Normal));
+    return output;
+}
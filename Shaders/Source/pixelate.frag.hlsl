@@ -0,0 +1,14 @@
+Texture2D<float4> Texture : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float4 Params; // x: pixel size in screen pixels
+    float2 TexelSize;
+    float2 Padding;
+};
+
+float4 main(float2 TexCoord : TEXCOORD0) : SV_Target0 {
+    float2 block = TexelSize * max(Params.x, 1.0f);
+    float2 texcoord = (floor(TexCoord / block) + 0.5f) * block;
+    return Texture.Sample(Sampler, texcoord);
+}
@@ -0,0 +1,26 @@
+Texture2D<float4> Texture : register(t0, space2);
+Texture2D<float4> NormalMap : register(t1, space2);
+SamplerState Sampler : register(s0, space2);
+SamplerState NormalSampler : register(s1, space2);
+
+struct Input
+{
+    float2 TexCoord : TEXCOORD0;
+    float4 Color : TEXCOORD1;
+    float NormalMap : TEXCOORD2;
+};
+
+float4 main(Input input) : SV_Target0
+{
+    // Transparent texels must not overwrite the normals of sprites underneath
+    if (input.Color.a * Texture.Sample(Sampler, input.TexCoord).a < 0.5f) {
+        discard;
+    }
+
+    float3 normal = float3(0.5f, 0.5f, 1.0f);
+    if (input.NormalMap > 0.5f) {
+        normal = NormalMap.Sample(NormalSampler, input.TexCoord).rgb;
+    }
+
+    return float4(normal, 1.0f);
+}
@@ -2,7 +2,8 @@ struct SpriteData {
     float3 Position;
     float Rotation;
     float2 Scale;
-    float2 Padding;
+    float NormalMap;
+    float Padding;
     float TexU, TexV, TexW, TexH;
     float4 Color;
 };
@@ -10,6 +11,7 @@ struct SpriteData {
 struct Output {
     float2 Texcoord : TEXCOORD0;
     float4 Color    : TEXCOORD1;
+    float NormalMap : TEXCOORD2;
     float4 Position : SV_Position;
 };
 
@@ -55,5 +57,6 @@ Output main(uint id : SV_VertexID) {
     output.Position = mul(ViewProjectionMatrix, float4(worldPos, 1.0));
     output.Texcoord = texcoord;
     output.Color    = sprite.Color;
+    output.NormalMap = sprite.NormalMap;
     return output;
 }
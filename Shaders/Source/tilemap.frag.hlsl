@@ -0,0 +1,17 @@
+Texture2D<float4> Texture : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float4 Color : packoffset(c0);
+};
+
+struct Input {
+    float2 TexCoord : TEXCOORD0;
+};
+
+float4 main(Input input) : SV_Target0 {
+    float4 color = Color * Texture.Sample(Sampler, input.TexCoord);
+    // Tiles write depth, so see-through texels must not
+    clip(color.a - 0.5f);
+    return color;
+}
@@ -0,0 +1,62 @@
+struct Output {
+    float2 Texcoord : TEXCOORD0;
+    float4 Position : SV_Position;
+};
+
+// One tile per cell of the chunk, 0 is empty, otherwise tileset index + 1 with flip flags in
+// the top three bits
+StructuredBuffer<uint> Tiles : register(t0, space0);
+
+cbuffer UniformBlock : register(b0, space1) {
+    float4x4 ViewProjectionMatrix : packoffset(c0);
+    float4 Origin : packoffset(c4);     // xy: world position of the chunk, z: depth, w: chunk width in cells
+    float4 TileSize : packoffset(c5);   // xy: world size of a tile, z: tileset columns
+    float4 TileUV : packoffset(c6);     // xy: tile size in uv, zw: distance between tiles in uv
+    float4 UVOffset : packoffset(c7);   // xy: uv of the first tile
+};
+
+static const uint FLIP_HORIZONTAL = 0x80000000;
+static const uint FLIP_VERTICAL = 0x40000000;
+static const uint FLIP_DIAGONAL = 0x20000000;
+
+// Triangle indices for a quad (six vertices)
+static const uint triangleIndices[6] = { 0, 1, 2, 3, 2, 1 };
+
+Output main(uint id : SV_VertexID) {
+    uint cell = id / 6;
+    uint vert = triangleIndices[id % 6];
+    uint tile = Tiles[cell];
+    uint index = tile & 0x1FFFFFFF;
+
+    Output output;
+    output.Texcoord = float2(0.0f, 0.0f);
+    // Empty cells collapse to a point and produce no fragments
+    output.Position = float4(0.0f, 0.0f, 0.0f, 1.0f);
+    if (index == 0) {
+        return output;
+    }
+
+    float2 corner = float2((vert & 1), (vert >> 1));
+    uint chunkWidth = (uint)Origin.w;
+    float2 cellPos = float2(cell % chunkWidth, cell / chunkWidth);
+    float2 worldPos = Origin.xy + (cellPos + corner) * TileSize.xy;
+
+    // Same order as Tiled: swap the axes first, then mirror
+    float2 uvCorner = corner;
+    if (tile & FLIP_DIAGONAL) {
+        uvCorner = uvCorner.yx;
+    }
+    if (tile & FLIP_HORIZONTAL) {
+        uvCorner.x = 1.0f - uvCorner.x;
+    }
+    if (tile & FLIP_VERTICAL) {
+        uvCorner.y = 1.0f - uvCorner.y;
+    }
+
+    uint columns = (uint)TileSize.z;
+    float2 tileCell = float2((index - 1) % columns, (index - 1) / columns);
+
+    output.Texcoord = UVOffset.xy + tileCell * TileUV.zw + uvCorner * TileUV.xy;
+    output.Position = mul(ViewProjectionMatrix, float4(worldPos, Origin.z, 1.0f));
+    return output;
+}
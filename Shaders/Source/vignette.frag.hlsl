@@ -0,0 +1,15 @@
+Texture2D<float4> Texture : register(t0, space2);
+SamplerState Sampler : register(s0, space2);
+
+cbuffer UniformBlock : register(b0, space3) {
+    float4 Params; // x: intensity, y: radius, z: softness
+    float2 TexelSize;
+    float2 Padding;
+};
+
+float4 main(float2 TexCoord : TEXCOORD0) : SV_Target0 {
+    float4 color = Texture.Sample(Sampler, TexCoord);
+    float dist = distance(TexCoord, float2(0.5f, 0.5f));
+    float vignette = smoothstep(Params.y, Params.y - Params.z, dist);
+    return float4(color.rgb * lerp(1.0f, vignette, Params.x), color.a);
+}
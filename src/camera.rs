@@ -1,18 +1,190 @@
-use flecs_ecs::macros::Component;
-use glam::{Mat4, Vec3};
+use flecs_ecs::{
+    core::{Entity, EntityView, World, WorldGet},
+    macros::Component,
+};
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use sdl3_sys::gpu::SDL_GPUViewport;
 
+/// The matrices renderers draw with. A `Camera2D` on the same entity rebuilds them every frame.
+/// Entities with `Camera` and `CameraView` are drawn by the renderer, one after the other.
 #[repr(C)]
 #[derive(Component)]
-pub struct Camera(pub Mat4);
+pub struct Camera {
+    /// `projection * view`, the matrix pushed to the shaders.
+    pub view_projection: Mat4,
+    pub projection: Mat4,
+    pub view: Mat4,
+    pub near: f32,
+    pub far: f32,
+}
+
+/// What a camera does to its viewport before drawing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraClear {
+    /// Fill the viewport with a color and reset depth.
+    Color(f32, f32, f32),
+    /// Only reset depth, for cameras drawn over others like picture-in-picture.
+    DepthOnly,
+    /// Keep color and depth from the cameras drawn before.
+    None,
+}
+
+/// Where and in which order a camera is drawn.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CameraView {
+    /// Area of the window as fractions `(x, y, width, height)`.
+    pub rect: Vec4,
+    pub clear: CameraClear,
+    /// Cameras are drawn in ascending priority, later ones on top.
+    pub priority: i32,
+    /// Only what has a `RenderLayers` bit in common with the mask is drawn.
+    pub layer_mask: u32,
+    /// The area in pixels, updated every frame by `ScalingModule`.
+    pub viewport: SDL_GPUViewport,
+}
+
+/// The camera whole-screen effects follow, post-processing and lighting read their settings
+/// and view from it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MainCamera(pub Entity);
+
+/// Bit mask of the camera layers an entity is drawn on, entities without it are on layer 1.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderLayers(pub u32);
+
+impl Default for CameraView {
+    fn default() -> Self {
+        Self {
+            rect: Vec4::new(0.0, 0.0, 1.0, 1.0),
+            clear: CameraClear::DepthOnly,
+            priority: 0,
+            layer_mask: u32::MAX,
+            viewport: SDL_GPUViewport {
+                x: 0.0,
+                y: 0.0,
+                w: 0.0,
+                h: 0.0,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            },
+        }
+    }
+}
+
+impl CameraView {
+    pub fn new(rect: Vec4, priority: i32) -> Self {
+        Self {
+            rect,
+            priority,
+            ..Default::default()
+        }
+    }
+
+    /// `viewport` as `(x, y, width, height)`, the form `Camera::screen_to_world` takes.
+    pub fn viewport_rect(&self) -> Vec4 {
+        Vec4::new(self.viewport.x, self.viewport.y, self.viewport.w, self.viewport.h)
+    }
+
+    pub fn contains(&self, screen: Vec2) -> bool {
+        screen.x >= self.viewport.x
+            && screen.y >= self.viewport.y
+            && screen.x < self.viewport.x + self.viewport.w
+            && screen.y < self.viewport.y + self.viewport.h
+    }
+}
+
+impl MainCamera {
+    /// The main camera entity, `None` when the singleton is not set or its entity is gone.
+    pub fn entity(world: &World) -> Option<EntityView<'_>> {
+        let mut camera = None;
+        world.get::<Option<&MainCamera>>(|main_camera| {
+            camera = main_camera.map(|main_camera| main_camera.0);
+        });
+
+        camera
+            .map(|camera| world.entity_from_id(camera))
+            .filter(|camera| camera.is_alive())
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self(1)
+    }
+}
 
 impl Camera {
     pub fn new(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
-        Self(Mat4::orthographic_lh(left, right, bottom, top, near, far))
+        let projection = Mat4::orthographic_lh(left, right, bottom, top, near, far);
+
+        Self {
+            view_projection: projection,
+            projection,
+            view: Mat4::IDENTITY,
+            near,
+            far,
+        }
+    }
+
+    /// Replaces the orthographic projection, keeping the view and depth range.
+    pub fn set_projection(&mut self, left: f32, right: f32, bottom: f32, top: f32) {
+        self.projection = Mat4::orthographic_lh(left, right, bottom, top, self.near, self.far);
+        self.view_projection = self.projection * self.view;
+    }
+
+    /// A perspective camera, `fov_y` is in radians. Depth goes from 0 at `near` to 1 at `far`.
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let projection = Mat4::perspective_lh(fov_y, aspect, near, far);
+
+        Self {
+            view_projection: projection,
+            projection,
+            view: Mat4::IDENTITY,
+            near,
+            far,
+        }
     }
 
-    pub fn translate(&mut self, translation: Vec3) {
-        let translation_matrix = Mat4::from_translation(translation);
+    /// Converts a position in window pixels to world space. `viewport` is the pixel area the
+    /// camera renders to as `(x, y, width, height)`.
+    pub fn screen_to_world(&self, screen: Vec2, viewport: Vec4) -> Vec3 {
+        let ndc = Vec2::new(
+            (screen.x - viewport.x) / viewport.z * 2.0 - 1.0,
+            1.0 - (screen.y - viewport.y) / viewport.w * 2.0,
+        );
 
-        self.0 *= translation_matrix;
+        self.view_projection.inverse().project_point3(ndc.extend(0.0))
     }
+
+    /// Converts a world position to window pixels inside `viewport`, see `screen_to_world`.
+    pub fn world_to_screen(&self, position: Vec3, viewport: Vec4) -> Vec2 {
+        let ndc = self.view_projection.project_point3(position);
+
+        Vec2::new(
+            viewport.x + (ndc.x + 1.0) * 0.5 * viewport.z,
+            viewport.y + (1.0 - ndc.y) * 0.5 * viewport.w,
+        )
+    }
+
+    /// World area the camera can see, see `visible_rect`.
+    pub fn visible_rect(&self) -> Option<(Vec2, Vec2)> {
+        visible_rect(self.view_projection)
+    }
+}
+
+/// World area a view projection can see on the xy plane as `(min, max)`, from both ends of the
+/// depth range. `None` when the matrix can not be inverted, callers should then cull nothing.
+pub fn visible_rect(view_projection: Mat4) -> Option<(Vec2, Vec2)> {
+    let inverse = view_projection.inverse();
+    let mut min = Vec2::splat(f32::MAX);
+    let mut max = Vec2::splat(f32::MIN);
+    for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+        for depth in [0.0, 1.0] {
+            let point = inverse.project_point3(Vec3::new(x, y, depth)).truncate();
+            min = min.min(point);
+            max = max.max(point);
+        }
+    }
+
+    (min.is_finite() && max.is_finite()).then_some((min, max))
 }
@@ -0,0 +1,420 @@
+use std::ffi::{c_char, CStr};
+
+use flecs_ecs::{
+    core::{flecs, World, WorldGet},
+    macros::Component,
+    prelude::Module,
+};
+use sdl3_sys::{
+    events::{SDL_Event, SDL_EventType},
+    keyboard::SDL_KeyboardID,
+    keycode::{SDL_Keycode, SDL_Keymod},
+    mouse::{SDL_MouseButtonFlags, SDL_MOUSEWHEEL_FLIPPED},
+    joystick::SDL_JoystickID,
+    scancode::SDL_Scancode,
+    video::{SDL_DisplayID, SDL_WindowID},
+};
+
+use crate::window::{Window, WindowResizedEvent};
+
+/// Registers the typed events `forward_event` emits. Systems react to them with observers, e.g.
+/// `observer!("name", world, KeyboardEvent, flecs::Any)`.
+#[derive(Component)]
+pub struct EventsModule;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct QuitEvent;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowEventKind {
+    Shown,
+    Hidden,
+    Exposed,
+    Moved,
+    Resized,
+    PixelSizeChanged,
+    Minimized,
+    Maximized,
+    Restored,
+    MouseEnter,
+    MouseLeave,
+    FocusGained,
+    FocusLost,
+    CloseRequested,
+    DisplayChanged,
+    DisplayScaleChanged,
+    Occluded,
+    EnterFullscreen,
+    LeaveFullscreen,
+    Destroyed,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WindowEvent {
+    pub kind: WindowEventKind,
+    pub window_id: SDL_WindowID,
+    /// Event dependent, e.g. the new size for `Resized` or the position for `Moved`.
+    pub data1: i32,
+    pub data2: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayEventKind {
+    Added,
+    Removed,
+    Moved,
+    OrientationChanged,
+    ContentScaleChanged,
+    DesktopModeChanged,
+    CurrentModeChanged,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DisplayEvent {
+    pub kind: DisplayEventKind,
+    pub display_id: SDL_DisplayID,
+    pub data1: i32,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct KeyboardEvent {
+    pub keyboard: SDL_KeyboardID,
+    pub scancode: SDL_Scancode,
+    pub keycode: SDL_Keycode,
+    pub modifiers: SDL_Keymod,
+    pub down: bool,
+    pub repeat: bool,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct TextInputEvent {
+    pub text: String,
+}
+
+/// Uncommitted IME composition, `start` and `length` are the selected range within `text`.
+#[derive(Component, Clone, Debug)]
+pub struct TextEditingEvent {
+    pub text: String,
+    pub start: i32,
+    pub length: i32,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MouseMotionEvent {
+    pub x: f32,
+    pub y: f32,
+    pub xrel: f32,
+    pub yrel: f32,
+    pub state: SDL_MouseButtonFlags,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MouseButtonEvent {
+    /// `SDL_BUTTON_LEFT`, `SDL_BUTTON_MIDDLE`, `SDL_BUTTON_RIGHT`, ...
+    pub button: u8,
+    pub down: bool,
+    pub clicks: u8,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Wheel amounts are already corrected for flipped (natural) scrolling.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MouseWheelEvent {
+    pub x: f32,
+    pub y: f32,
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadDeviceEventKind {
+    Added,
+    Removed,
+    Remapped,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GamepadDeviceEvent {
+    pub kind: GamepadDeviceEventKind,
+    pub which: SDL_JoystickID,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoystickDeviceEventKind {
+    Added,
+    Removed,
+}
+
+/// Sent for every joystick, gamepads included, see `GamepadDeviceEvent` for those.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct JoystickDeviceEvent {
+    pub kind: JoystickDeviceEventKind,
+    pub which: SDL_JoystickID,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GamepadButtonEvent {
+    pub which: SDL_JoystickID,
+    /// An `SDL_GamepadButton` value.
+    pub button: u8,
+    pub down: bool,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GamepadAxisEvent {
+    pub which: SDL_JoystickID,
+    /// An `SDL_GamepadAxis` value.
+    pub axis: u8,
+    pub value: i16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropEventKind {
+    Begin,
+    File,
+    Text,
+    Position,
+    Complete,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct DropEvent {
+    pub kind: DropEventKind,
+    pub x: f32,
+    pub y: f32,
+    /// The file path for `File`, the dropped text for `Text`, `None` otherwise.
+    pub data: Option<String>,
+}
+
+unsafe fn to_string(text: *const c_char) -> Option<String> {
+    if text.is_null() {
+        return None;
+    }
+
+    Some(CStr::from_ptr(text).to_string_lossy().into_owned())
+}
+
+fn window_event_kind(event_type: SDL_EventType) -> Option<WindowEventKind> {
+    let kind = match event_type {
+        SDL_EventType::WINDOW_SHOWN => WindowEventKind::Shown,
+        SDL_EventType::WINDOW_HIDDEN => WindowEventKind::Hidden,
+        SDL_EventType::WINDOW_EXPOSED => WindowEventKind::Exposed,
+        SDL_EventType::WINDOW_MOVED => WindowEventKind::Moved,
+        SDL_EventType::WINDOW_RESIZED => WindowEventKind::Resized,
+        SDL_EventType::WINDOW_PIXEL_SIZE_CHANGED => WindowEventKind::PixelSizeChanged,
+        SDL_EventType::WINDOW_MINIMIZED => WindowEventKind::Minimized,
+        SDL_EventType::WINDOW_MAXIMIZED => WindowEventKind::Maximized,
+        SDL_EventType::WINDOW_RESTORED => WindowEventKind::Restored,
+        SDL_EventType::WINDOW_MOUSE_ENTER => WindowEventKind::MouseEnter,
+        SDL_EventType::WINDOW_MOUSE_LEAVE => WindowEventKind::MouseLeave,
+        SDL_EventType::WINDOW_FOCUS_GAINED => WindowEventKind::FocusGained,
+        SDL_EventType::WINDOW_FOCUS_LOST => WindowEventKind::FocusLost,
+        SDL_EventType::WINDOW_CLOSE_REQUESTED => WindowEventKind::CloseRequested,
+        SDL_EventType::WINDOW_DISPLAY_CHANGED => WindowEventKind::DisplayChanged,
+        SDL_EventType::WINDOW_DISPLAY_SCALE_CHANGED => WindowEventKind::DisplayScaleChanged,
+        SDL_EventType::WINDOW_OCCLUDED => WindowEventKind::Occluded,
+        SDL_EventType::WINDOW_ENTER_FULLSCREEN => WindowEventKind::EnterFullscreen,
+        SDL_EventType::WINDOW_LEAVE_FULLSCREEN => WindowEventKind::LeaveFullscreen,
+        SDL_EventType::WINDOW_DESTROYED => WindowEventKind::Destroyed,
+        _ => {
+            return None;
+        }
+    };
+
+    Some(kind)
+}
+
+fn display_event_kind(event_type: SDL_EventType) -> Option<DisplayEventKind> {
+    let kind = match event_type {
+        SDL_EventType::DISPLAY_ADDED => DisplayEventKind::Added,
+        SDL_EventType::DISPLAY_REMOVED => DisplayEventKind::Removed,
+        SDL_EventType::DISPLAY_MOVED => DisplayEventKind::Moved,
+        SDL_EventType::DISPLAY_ORIENTATION => DisplayEventKind::OrientationChanged,
+        SDL_EventType::DISPLAY_CONTENT_SCALE_CHANGED => DisplayEventKind::ContentScaleChanged,
+        SDL_EventType::DISPLAY_DESKTOP_MODE_CHANGED => DisplayEventKind::DesktopModeChanged,
+        SDL_EventType::DISPLAY_CURRENT_MODE_CHANGED => DisplayEventKind::CurrentModeChanged,
+        _ => {
+            return None;
+        }
+    };
+
+    Some(kind)
+}
+
+/// Translates an SDL event into its typed counterpart and emits it into the world. Events
+/// without a typed counterpart are ignored.
+pub fn forward_event(world: &World, event: &SDL_Event) {
+    let event_type = SDL_EventType(unsafe { event.r#type });
+
+    unsafe {
+        if let Some(kind) = window_event_kind(event_type) {
+            world.event().entity(flecs::Any).emit(&WindowEvent {
+                kind,
+                window_id: event.window.windowID,
+                data1: event.window.data1,
+                data2: event.window.data2,
+            });
+
+            if kind == WindowEventKind::PixelSizeChanged
+                || kind == WindowEventKind::DisplayScaleChanged
+            {
+                world.get::<&Window>(|window| {
+                    let resized = WindowResizedEvent { size: window.size() };
+                    world.event().entity(flecs::Any).emit(&resized);
+                });
+            }
+            return;
+        }
+
+        if let Some(kind) = display_event_kind(event_type) {
+            world.event().entity(flecs::Any).emit(&DisplayEvent {
+                kind,
+                display_id: event.display.displayID,
+                data1: event.display.data1,
+            });
+            return;
+        }
+
+        match event_type {
+            SDL_EventType::QUIT => {
+                world.event().entity(flecs::Any).emit(&QuitEvent);
+            }
+            SDL_EventType::KEY_DOWN | SDL_EventType::KEY_UP => {
+                world.event().entity(flecs::Any).emit(&KeyboardEvent {
+                    keyboard: event.key.which,
+                    scancode: event.key.scancode,
+                    keycode: event.key.key,
+                    modifiers: event.key.r#mod,
+                    down: event.key.down,
+                    repeat: event.key.repeat,
+                });
+            }
+            SDL_EventType::TEXT_INPUT => {
+                world.event().entity(flecs::Any).emit(&TextInputEvent {
+                    text: to_string(event.text.text).unwrap_or_default(),
+                });
+            }
+            SDL_EventType::TEXT_EDITING => {
+                world.event().entity(flecs::Any).emit(&TextEditingEvent {
+                    text: to_string(event.edit.text).unwrap_or_default(),
+                    start: event.edit.start,
+                    length: event.edit.length,
+                });
+            }
+            SDL_EventType::MOUSE_MOTION => {
+                world.event().entity(flecs::Any).emit(&MouseMotionEvent {
+                    x: event.motion.x,
+                    y: event.motion.y,
+                    xrel: event.motion.xrel,
+                    yrel: event.motion.yrel,
+                    state: event.motion.state,
+                });
+            }
+            SDL_EventType::MOUSE_BUTTON_DOWN | SDL_EventType::MOUSE_BUTTON_UP => {
+                world.event().entity(flecs::Any).emit(&MouseButtonEvent {
+                    button: event.button.button,
+                    down: event.button.down,
+                    clicks: event.button.clicks,
+                    x: event.button.x,
+                    y: event.button.y,
+                });
+            }
+            SDL_EventType::MOUSE_WHEEL => {
+                let flip = if event.wheel.direction == SDL_MOUSEWHEEL_FLIPPED {
+                    -1.0
+                } else {
+                    1.0
+                };
+
+                world.event().entity(flecs::Any).emit(&MouseWheelEvent {
+                    x: event.wheel.x * flip,
+                    y: event.wheel.y * flip,
+                    mouse_x: event.wheel.mouse_x,
+                    mouse_y: event.wheel.mouse_y,
+                });
+            }
+            SDL_EventType::GAMEPAD_ADDED
+            | SDL_EventType::GAMEPAD_REMOVED
+            | SDL_EventType::GAMEPAD_REMAPPED => {
+                let kind = match event_type {
+                    SDL_EventType::GAMEPAD_ADDED => GamepadDeviceEventKind::Added,
+                    SDL_EventType::GAMEPAD_REMOVED => GamepadDeviceEventKind::Removed,
+                    _ => GamepadDeviceEventKind::Remapped,
+                };
+
+                world.event().entity(flecs::Any).emit(&GamepadDeviceEvent {
+                    kind,
+                    which: event.gdevice.which,
+                });
+            }
+            SDL_EventType::GAMEPAD_BUTTON_DOWN | SDL_EventType::GAMEPAD_BUTTON_UP => {
+                world.event().entity(flecs::Any).emit(&GamepadButtonEvent {
+                    which: event.gbutton.which,
+                    button: event.gbutton.button,
+                    down: event.gbutton.down,
+                });
+            }
+            SDL_EventType::GAMEPAD_AXIS_MOTION => {
+                world.event().entity(flecs::Any).emit(&GamepadAxisEvent {
+                    which: event.gaxis.which,
+                    axis: event.gaxis.axis,
+                    value: event.gaxis.value,
+                });
+            }
+            SDL_EventType::JOYSTICK_ADDED | SDL_EventType::JOYSTICK_REMOVED => {
+                let kind = if event_type == SDL_EventType::JOYSTICK_ADDED {
+                    JoystickDeviceEventKind::Added
+                } else {
+                    JoystickDeviceEventKind::Removed
+                };
+
+                world.event().entity(flecs::Any).emit(&JoystickDeviceEvent {
+                    kind,
+                    which: event.jdevice.which,
+                });
+            }
+            SDL_EventType::DROP_BEGIN
+            | SDL_EventType::DROP_FILE
+            | SDL_EventType::DROP_TEXT
+            | SDL_EventType::DROP_POSITION
+            | SDL_EventType::DROP_COMPLETE => {
+                let kind = match event_type {
+                    SDL_EventType::DROP_BEGIN => DropEventKind::Begin,
+                    SDL_EventType::DROP_FILE => DropEventKind::File,
+                    SDL_EventType::DROP_TEXT => DropEventKind::Text,
+                    SDL_EventType::DROP_POSITION => DropEventKind::Position,
+                    _ => DropEventKind::Complete,
+                };
+
+                world.event().entity(flecs::Any).emit(&DropEvent {
+                    kind,
+                    x: event.drop.x,
+                    y: event.drop.y,
+                    data: to_string(event.drop.data),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Module for EventsModule {
+    fn module(world: &World) {
+        world.component::<QuitEvent>();
+        world.component::<WindowEvent>();
+        world.component::<WindowResizedEvent>();
+        world.component::<DisplayEvent>();
+        world.component::<KeyboardEvent>();
+        world.component::<TextInputEvent>();
+        world.component::<TextEditingEvent>();
+        world.component::<MouseMotionEvent>();
+        world.component::<MouseButtonEvent>();
+        world.component::<MouseWheelEvent>();
+        world.component::<GamepadDeviceEvent>();
+        world.component::<GamepadButtonEvent>();
+        world.component::<GamepadAxisEvent>();
+        world.component::<JoystickDeviceEvent>();
+        world.component::<DropEvent>();
+    }
+}
@@ -1,35 +1,122 @@
-use std::{ffi::CStr, ptr::null_mut};
+use std::{
+    ffi::{c_void, CStr},
+    ptr::null_mut,
+};
 
 use flecs_ecs::{
-    core::{flecs, World},
-    macros::Component,
+    core::{
+        flecs::{
+            self,
+            pipeline::{OnStore, PostUpdate},
+        },
+        Entity, World, WorldGet,
+    },
+    macros::{observer, system, Component},
+    prelude::{Builder, Module, QueryAPI, QueryBuilderImpl, SystemAPI},
+};
+use glam::{Mat4, Vec4};
+use sdl3_sys::{
+    error::SDL_GetError,
+    gpu::*,
+    pixels::{SDL_FColor, SDL_PIXELFORMAT_ABGR8888},
+    rect::SDL_Rect,
+    stdinc::SDL_memcpy,
+    surface::{SDL_CreateSurface, SDL_DestroySurface, SDL_Surface},
+    video::*,
+};
+
+use crate::{
+    camera::{Camera, CameraClear, CameraView, MainCamera},
+    load_shader,
+    window::Window,
 };
-use sdl3_sys::{error::SDL_GetError, gpu::*, pixels::SDL_FColor, video::*};
+
+#[derive(Component)]
+pub struct GpuModule;
 
 #[derive(Debug, Component)]
 pub struct GpuApi {
     pub gpu_device: *mut SDL_GPUDevice,
     pub color: (f32, f32, f32),
+    /// MSAA sample count of the shared render pass, see `GpuApi::set_sample_count`.
+    pub sample_count: SDL_GPUSampleCount,
+}
+
+/// Format of the depth buffer attached to the shared render pass. Pipelines drawing from a
+/// `RenderEvent` must declare it as their depth-stencil target format.
+pub const DEPTH_FORMAT: SDL_GPUTextureFormat = SDL_GPU_TEXTUREFORMAT_D16_UNORM;
+
+/// State of the frame currently being recorded. The command buffer is acquired at `PostUpdate`
+/// so modules can record copy passes at `PreStore`, and it is submitted once at `OnStore`.
+#[derive(Component)]
+pub struct GpuFrame {
+    pub command_buffer: *mut SDL_GPUCommandBuffer,
+    pub swapchain_texture: *mut SDL_GPUTexture,
+    /// Texture the shared render pass draws into. Defaults to the swapchain texture and is
+    /// redirected to an offscreen target when post-processing is active.
+    pub color_target: *mut SDL_GPUTexture,
+    /// Depth buffer of the shared render pass, recreated when the swapchain size changes.
+    pub depth_texture: *mut SDL_GPUTexture,
+    /// Multisampled color target resolved into `color_target`, null without MSAA.
+    pub msaa_texture: *mut SDL_GPUTexture,
+    pub width: u32,
+    pub height: u32,
 }
 
+/// Emitted once per camera with the shared render pass, after its viewport and scissor were
+/// set and it was cleared. Render modules observe it to draw what the camera sees.
 #[derive(Component)]
 pub struct RenderEvent {
     pub command_buffer: *mut SDL_GPUCommandBuffer,
     pub render_pass: *mut SDL_GPURenderPass,
+    pub camera: Entity,
+    pub view_projection: Mat4,
+    /// `CameraView::layer_mask` of the camera.
+    pub layer_mask: u32,
+    pub viewport: SDL_GPUViewport,
+}
+
+/// Emitted after the shared render pass ended when it drew into an offscreen `color_target`.
+/// Observers are responsible for getting `scene_texture` onto `swapchain_texture`.
+#[derive(Component)]
+pub struct PostRenderEvent {
+    pub command_buffer: *mut SDL_GPUCommandBuffer,
+    pub scene_texture: *mut SDL_GPUTexture,
+    pub swapchain_texture: *mut SDL_GPUTexture,
+    pub width: u32,
+    pub height: u32,
 }
 
 #[derive(Component)]
 pub struct ShadersInitEvent {
     pub gpu_device: *mut SDL_GPUDevice,
     pub window: *mut SDL_Window,
+    /// Pipelines drawing from a `RenderEvent` must use this as their multisample count.
+    pub sample_count: SDL_GPUSampleCount,
+}
+
+/// Pipelines filling a camera viewport from within the shared render pass, see `CameraClear`.
+#[derive(Component)]
+pub struct ClearPipelines {
+    pub color: *mut SDL_GPUGraphicsPipeline,
+    pub depth_only: *mut SDL_GPUGraphicsPipeline,
 }
 
 unsafe impl Send for GpuApi {}
 unsafe impl Sync for GpuApi {}
 
+unsafe impl Send for GpuFrame {}
+unsafe impl Sync for GpuFrame {}
+
 unsafe impl Send for RenderEvent {}
 unsafe impl Sync for RenderEvent {}
 
+unsafe impl Send for PostRenderEvent {}
+unsafe impl Sync for PostRenderEvent {}
+
+unsafe impl Send for ClearPipelines {}
+unsafe impl Sync for ClearPipelines {}
+
 unsafe impl Send for ShadersInitEvent {}
 unsafe impl Sync for ShadersInitEvent {}
 
@@ -60,6 +147,7 @@ impl GpuApi {
             Self {
                 gpu_device,
                 color: (0.2, 0.3, 0.3),
+                sample_count: SDL_GPU_SAMPLECOUNT_1,
             }
         }
     }
@@ -68,11 +156,572 @@ impl GpuApi {
         self.color = color;
     }
 
+    /// Requests `samples` MSAA samples (1, 2, 4 or 8), falling back to the highest count the
+    /// swapchain format supports. Must be called before `init` since pipelines are created
+    /// with the sample count. Returns the sample count in use.
+    pub fn set_sample_count(&mut self, window: *mut SDL_Window, samples: u32) -> u32 {
+        let candidates = [
+            (8, SDL_GPU_SAMPLECOUNT_8),
+            (4, SDL_GPU_SAMPLECOUNT_4),
+            (2, SDL_GPU_SAMPLECOUNT_2),
+        ];
+
+        unsafe {
+            let format = SDL_GetGPUSwapchainTextureFormat(self.gpu_device, window);
+            for (count, sample_count) in candidates {
+                if count <= samples
+                    && SDL_GPUTextureSupportsSampleCount(self.gpu_device, format, sample_count)
+                    && SDL_GPUTextureSupportsSampleCount(self.gpu_device, DEPTH_FORMAT, sample_count)
+                {
+                    self.sample_count = sample_count;
+                    return count;
+                }
+            }
+        }
+
+        self.sample_count = SDL_GPU_SAMPLECOUNT_1;
+        1
+    }
+
     pub fn init(&self, world: &World, window: *mut SDL_Window) {
         let event = ShadersInitEvent {
             gpu_device: self.gpu_device,
             window,
+            sample_count: self.sample_count,
         };
         world.event().entity(flecs::Any).emit(&event);
     }
 }
+
+/// Creates a sampled RGBA8 texture from a surface already converted by `load_image`.
+/// The surface is not destroyed.
+pub fn upload_texture(gpu_device: *mut SDL_GPUDevice, image: *mut SDL_Surface) -> *mut SDL_GPUTexture {
+    unsafe {
+        let width = (*image).w as u32;
+        let height = (*image).h as u32;
+
+        let texture = SDL_CreateGPUTexture(
+            gpu_device,
+            &(SDL_GPUTextureCreateInfo {
+                r#type: SDL_GPU_TEXTURETYPE_2D,
+                format: SDL_GPU_TEXTUREFORMAT_R8G8B8A8_UNORM,
+                width,
+                height,
+                layer_count_or_depth: 1,
+                num_levels: 1,
+                usage: SDL_GPU_TEXTUREUSAGE_SAMPLER,
+                ..Default::default()
+            }),
+        );
+        if texture == null_mut() {
+            let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
+            panic!("Failed to create texture: {:?}", error);
+        }
+
+        let texture_transfer_buffer = SDL_CreateGPUTransferBuffer(
+            gpu_device,
+            &(SDL_GPUTransferBufferCreateInfo {
+                usage: SDL_GPU_TRANSFERBUFFERUSAGE_UPLOAD,
+                size: width * height * 4,
+                ..Default::default()
+            }),
+        );
+
+        // Rows are copied one by one because the surface pitch can be larger than `w * 4`.
+        let texture_transfer_ptr =
+            SDL_MapGPUTransferBuffer(gpu_device, texture_transfer_buffer, false) as *mut u8;
+        for row in 0..height as usize {
+            SDL_memcpy(
+                texture_transfer_ptr.add(row * width as usize * 4) as *mut _,
+                ((*image).pixels as *const u8).add(row * (*image).pitch as usize) as *const _,
+                width as usize * 4,
+            );
+        }
+        SDL_UnmapGPUTransferBuffer(gpu_device, texture_transfer_buffer);
+
+        let command_buffer = SDL_AcquireGPUCommandBuffer(gpu_device);
+        let copy_pass = SDL_BeginGPUCopyPass(command_buffer);
+
+        SDL_UploadToGPUTexture(
+            copy_pass,
+            &(SDL_GPUTextureTransferInfo {
+                transfer_buffer: texture_transfer_buffer,
+                offset: 0,
+                ..Default::default()
+            }),
+            &(SDL_GPUTextureRegion {
+                texture,
+                w: width,
+                h: height,
+                d: 1,
+                ..Default::default()
+            }),
+            false,
+        );
+
+        SDL_EndGPUCopyPass(copy_pass);
+        SDL_SubmitGPUCommandBuffer(command_buffer);
+        SDL_ReleaseGPUTransferBuffer(gpu_device, texture_transfer_buffer);
+
+        texture
+    }
+}
+
+/// Creates a sampled texture from tightly packed RGBA8 pixels through `upload_texture`, for
+/// images decoded outside of SDL. Returns null when `pixels` is shorter than `width * height * 4`
+/// or the surface can not be created.
+pub fn upload_rgba(
+    gpu_device: *mut SDL_GPUDevice,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> *mut SDL_GPUTexture {
+    let size = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|size| size.checked_mul(4));
+    if size.map_or(true, |size| pixels.len() < size) {
+        println!("Expected {}x{} RGBA pixels, got {} bytes", width, height, pixels.len());
+        return null_mut();
+    }
+
+    unsafe {
+        let surface = SDL_CreateSurface(width as i32, height as i32, SDL_PIXELFORMAT_ABGR8888);
+        if surface == null_mut() {
+            let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
+            println!("Failed to create surface: {:?}", error);
+            return null_mut();
+        }
+
+        // Surface rows can be padded past `width * 4`
+        let row_size = width as usize * 4;
+        let pitch = (*surface).pitch as usize;
+        for row in 0..height as usize {
+            SDL_memcpy(
+                ((*surface).pixels as *mut u8).add(row * pitch) as *mut c_void,
+                pixels[row * row_size..].as_ptr() as *const c_void,
+                row_size,
+            );
+        }
+
+        let texture = upload_texture(gpu_device, surface);
+        SDL_DestroySurface(surface);
+        texture
+    }
+}
+
+/// Creates a GPU buffer with `usage` filled with `data`, submitting its own command buffer like
+/// `upload_texture`.
+pub fn upload_buffer(
+    gpu_device: *mut SDL_GPUDevice,
+    usage: SDL_GPUBufferUsageFlags,
+    data: &[u8],
+) -> *mut SDL_GPUBuffer {
+    unsafe {
+        let size = data.len() as u32;
+
+        let buffer = SDL_CreateGPUBuffer(
+            gpu_device,
+            &(SDL_GPUBufferCreateInfo {
+                usage,
+                size,
+                ..Default::default()
+            }),
+        );
+        if buffer == null_mut() {
+            let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
+            panic!("Failed to create buffer: {:?}", error);
+        }
+
+        let transfer_buffer = SDL_CreateGPUTransferBuffer(
+            gpu_device,
+            &(SDL_GPUTransferBufferCreateInfo {
+                usage: SDL_GPU_TRANSFERBUFFERUSAGE_UPLOAD,
+                size,
+                ..Default::default()
+            }),
+        );
+
+        let transfer_ptr = SDL_MapGPUTransferBuffer(gpu_device, transfer_buffer, false);
+        SDL_memcpy(transfer_ptr, data.as_ptr() as *const _, data.len());
+        SDL_UnmapGPUTransferBuffer(gpu_device, transfer_buffer);
+
+        let command_buffer = SDL_AcquireGPUCommandBuffer(gpu_device);
+        let copy_pass = SDL_BeginGPUCopyPass(command_buffer);
+
+        SDL_UploadToGPUBuffer(
+            copy_pass,
+            &(SDL_GPUTransferBufferLocation {
+                transfer_buffer,
+                offset: 0,
+            }),
+            &(SDL_GPUBufferRegion {
+                buffer,
+                offset: 0,
+                size,
+            }),
+            false,
+        );
+
+        SDL_EndGPUCopyPass(copy_pass);
+        SDL_SubmitGPUCommandBuffer(command_buffer);
+        SDL_ReleaseGPUTransferBuffer(gpu_device, transfer_buffer);
+
+        buffer
+    }
+}
+
+fn create_clear_pipeline(
+    gpu_device: *mut SDL_GPUDevice,
+    format: SDL_GPUTextureFormat,
+    sample_count: SDL_GPUSampleCount,
+    vertex_shader: *mut SDL_GPUShader,
+    fragment_shader: *mut SDL_GPUShader,
+    write_color: bool,
+) -> *mut SDL_GPUGraphicsPipeline {
+    unsafe {
+        let pipeline = SDL_CreateGPUGraphicsPipeline(
+            gpu_device,
+            &(SDL_GPUGraphicsPipelineCreateInfo {
+                target_info: SDL_GPUGraphicsPipelineTargetInfo {
+                    num_color_targets: 1,
+                    color_target_descriptions: &(SDL_GPUColorTargetDescription {
+                        format,
+                        blend_state: SDL_GPUColorTargetBlendState {
+                            color_write_mask: if write_color {
+                                SDL_GPU_COLORCOMPONENT_R
+                                    | SDL_GPU_COLORCOMPONENT_G
+                                    | SDL_GPU_COLORCOMPONENT_B
+                                    | SDL_GPU_COLORCOMPONENT_A
+                            } else {
+                                0
+                            },
+                            enable_color_write_mask: true,
+                            ..Default::default()
+                        },
+                    }),
+                    depth_stencil_format: DEPTH_FORMAT,
+                    has_depth_stencil_target: true,
+                    ..Default::default()
+                },
+                depth_stencil_state: SDL_GPUDepthStencilState {
+                    compare_op: SDL_GPU_COMPAREOP_ALWAYS,
+                    enable_depth_test: true,
+                    enable_depth_write: true,
+                    ..Default::default()
+                },
+                multisample_state: SDL_GPUMultisampleState {
+                    sample_count,
+                    ..Default::default()
+                },
+                primitive_type: SDL_GPU_PRIMITIVETYPE_TRIANGLELIST,
+                vertex_shader,
+                fragment_shader,
+                ..Default::default()
+            }),
+        );
+        if pipeline == null_mut() {
+            let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
+            panic!("Failed to create clear pipeline: {:?}", error);
+        }
+
+        pipeline
+    }
+}
+
+impl Default for GpuFrame {
+    fn default() -> Self {
+        Self {
+            command_buffer: null_mut(),
+            swapchain_texture: null_mut(),
+            color_target: null_mut(),
+            depth_texture: null_mut(),
+            msaa_texture: null_mut(),
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+impl Module for GpuModule {
+    fn module(world: &World) {
+        world.component::<GpuFrame>();
+        world.component::<RenderEvent>();
+        world.component::<PostRenderEvent>();
+        world.component::<Camera>();
+        world.component::<CameraView>();
+        world.component::<MainCamera>();
+        world.component::<ClearPipelines>();
+
+        world.set(GpuFrame::default());
+
+        observer!("init_clear_pipelines", world, ShadersInitEvent, flecs::Any).each_iter(
+            |it, _, _| {
+                let event = &*it.param();
+                let gpu_device = event.gpu_device;
+
+                // Without `ClearPipelines`, cameras after the first one draw over what is below them
+                let vertex_shader = match load_shader(gpu_device, "fullscreen.vert", 0, 0, 0, 0) {
+                    Ok(shader) => shader,
+                    Err(error) => {
+                        println!("Camera clears disabled: {}", error);
+                        return;
+                    }
+                };
+                let fragment_shader = match load_shader(gpu_device, "clear.frag", 0, 1, 0, 0) {
+                    Ok(shader) => shader,
+                    Err(error) => {
+                        unsafe {
+                            SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                        }
+                        println!("Camera clears disabled: {}", error);
+                        return;
+                    }
+                };
+
+                unsafe {
+                    let format = SDL_GetGPUSwapchainTextureFormat(gpu_device, event.window);
+                    it.world().set(ClearPipelines {
+                        color: create_clear_pipeline(
+                            gpu_device,
+                            format,
+                            event.sample_count,
+                            vertex_shader,
+                            fragment_shader,
+                            true,
+                        ),
+                        depth_only: create_clear_pipeline(
+                            gpu_device,
+                            format,
+                            event.sample_count,
+                            vertex_shader,
+                            fragment_shader,
+                            false,
+                        ),
+                    });
+
+                    SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                    SDL_ReleaseGPUShader(gpu_device, fragment_shader);
+                }
+            },
+        );
+
+        let cameras = world
+            .query::<(&Camera, &CameraView)>()
+            .set_cached()
+            .build();
+
+        system!("gpu_begin_frame", world, &GpuApi($), &Window($), &mut GpuFrame($))
+            .kind::<PostUpdate>()
+            .each(|(gpu_api, window, frame)| unsafe {
+                let cmd_buf = SDL_AcquireGPUCommandBuffer(gpu_api.gpu_device);
+                if cmd_buf == null_mut() {
+                    let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
+                    panic!("Failed to acquire GPU command buffer: {:?}", error);
+                }
+
+                let mut swapchain_texture: *mut SDL_GPUTexture = null_mut();
+                let mut width = 0;
+                let mut height = 0;
+                if !SDL_WaitAndAcquireGPUSwapchainTexture(
+                    cmd_buf,
+                    window.0,
+                    &mut swapchain_texture,
+                    &mut width,
+                    &mut height,
+                ) {
+                    let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
+                    panic!("Failed to wait and acquire GPU swapchain texture: {:?}", error);
+                }
+
+                frame.command_buffer = cmd_buf;
+                frame.swapchain_texture = swapchain_texture;
+                frame.color_target = swapchain_texture;
+
+                if swapchain_texture == null_mut() {
+                    return;
+                }
+
+                if frame.depth_texture == null_mut() || frame.width != width || frame.height != height {
+                    if frame.depth_texture != null_mut() {
+                        SDL_ReleaseGPUTexture(gpu_api.gpu_device, frame.depth_texture);
+                    }
+                    if frame.msaa_texture != null_mut() {
+                        SDL_ReleaseGPUTexture(gpu_api.gpu_device, frame.msaa_texture);
+                        frame.msaa_texture = null_mut();
+                    }
+
+                    frame.depth_texture = SDL_CreateGPUTexture(
+                        gpu_api.gpu_device,
+                        &(SDL_GPUTextureCreateInfo {
+                            r#type: SDL_GPU_TEXTURETYPE_2D,
+                            format: DEPTH_FORMAT,
+                            width,
+                            height,
+                            layer_count_or_depth: 1,
+                            num_levels: 1,
+                            usage: SDL_GPU_TEXTUREUSAGE_DEPTH_STENCIL_TARGET,
+                            sample_count: gpu_api.sample_count,
+                            ..Default::default()
+                        }),
+                    );
+
+                    if gpu_api.sample_count != SDL_GPU_SAMPLECOUNT_1 {
+                        frame.msaa_texture = SDL_CreateGPUTexture(
+                            gpu_api.gpu_device,
+                            &(SDL_GPUTextureCreateInfo {
+                                r#type: SDL_GPU_TEXTURETYPE_2D,
+                                format: SDL_GetGPUSwapchainTextureFormat(
+                                    gpu_api.gpu_device,
+                                    window.0,
+                                ),
+                                width,
+                                height,
+                                layer_count_or_depth: 1,
+                                num_levels: 1,
+                                usage: SDL_GPU_TEXTUREUSAGE_COLOR_TARGET,
+                                sample_count: gpu_api.sample_count,
+                                ..Default::default()
+                            }),
+                        );
+                    }
+                }
+
+                frame.width = width;
+                frame.height = height;
+            });
+
+        system!("gpu_end_frame", world, &GpuApi($), &mut GpuFrame($))
+            .kind::<OnStore>()
+            .each_iter(move |it, _, (gpu_api, frame)| unsafe {
+                if frame.command_buffer == null_mut() {
+                    return;
+                }
+
+                // The swapchain texture is null when the window is minimized, in which case the
+                // copy passes recorded this frame are still submitted but nothing is drawn.
+                if frame.swapchain_texture != null_mut() {
+                    let color = gpu_api.color;
+                    let mut color_target_info = SDL_GPUColorTargetInfo::default();
+                    color_target_info.texture = frame.color_target;
+                    color_target_info.clear_color = SDL_FColor {
+                        r: color.0,
+                        g: color.1,
+                        b: color.2,
+                        a: 1.0,
+                    };
+                    color_target_info.load_op = SDL_GPU_LOADOP_CLEAR;
+                    color_target_info.store_op = SDL_GPU_STOREOP_STORE;
+
+                    if frame.msaa_texture != null_mut() {
+                        color_target_info.texture = frame.msaa_texture;
+                        color_target_info.resolve_texture = frame.color_target;
+                        color_target_info.store_op = SDL_GPU_STOREOP_RESOLVE;
+                    }
+
+                    let depth_target_info = SDL_GPUDepthStencilTargetInfo {
+                        texture: frame.depth_texture,
+                        clear_depth: 1.0,
+                        load_op: SDL_GPU_LOADOP_CLEAR,
+                        store_op: SDL_GPU_STOREOP_DONT_CARE,
+                        stencil_load_op: SDL_GPU_LOADOP_DONT_CARE,
+                        stencil_store_op: SDL_GPU_STOREOP_DONT_CARE,
+                        cycle: true,
+                        ..Default::default()
+                    };
+
+                    let render_pass = SDL_BeginGPURenderPass(
+                        frame.command_buffer,
+                        &color_target_info,
+                        1,
+                        &depth_target_info,
+                    );
+
+                    let mut views: Vec<(Entity, Mat4, CameraView)> = Vec::new();
+                    cameras.each_entity(|entity, (camera, view)| {
+                        views.push((entity.id(), camera.view_projection, *view));
+                    });
+                    views.sort_by(|a, b| a.2.priority.cmp(&b.2.priority).then((*a.0).cmp(&*b.0)));
+
+                    for (camera, view_projection, view) in views {
+                        // Cameras not sized by `ScalingModule` yet fall back to their rect
+                        let mut viewport = view.viewport;
+                        if viewport.w <= 0.0 || viewport.h <= 0.0 {
+                            let size = Vec4::new(
+                                frame.width as f32,
+                                frame.height as f32,
+                                frame.width as f32,
+                                frame.height as f32,
+                            );
+                            let rect = view.rect * size;
+                            viewport.x = rect.x;
+                            viewport.y = rect.y;
+                            viewport.w = rect.z;
+                            viewport.h = rect.w;
+                        }
+
+                        SDL_SetGPUViewport(render_pass, &viewport);
+                        SDL_SetGPUScissor(
+                            render_pass,
+                            &SDL_Rect {
+                                x: viewport.x as i32,
+                                y: viewport.y as i32,
+                                w: viewport.w as i32,
+                                h: viewport.h as i32,
+                            },
+                        );
+
+                        it.world().get::<Option<&ClearPipelines>>(|clear_pipelines| {
+                            let Some(clear_pipelines) = clear_pipelines else {
+                                return;
+                            };
+
+                            let (pipeline, color) = match view.clear {
+                                CameraClear::Color(r, g, b) => {
+                                    (clear_pipelines.color, Vec4::new(r, g, b, 1.0))
+                                }
+                                CameraClear::DepthOnly => (clear_pipelines.depth_only, Vec4::ZERO),
+                                CameraClear::None => {
+                                    return;
+                                }
+                            };
+
+                            SDL_BindGPUGraphicsPipeline(render_pass, pipeline);
+                            SDL_PushGPUFragmentUniformData(
+                                frame.command_buffer,
+                                0,
+                                &color as *const _ as *const _,
+                                size_of::<Vec4>() as u32,
+                            );
+                            SDL_DrawGPUPrimitives(render_pass, 3, 1, 0, 0);
+                        });
+
+                        it.world().event().entity(flecs::Any).emit(&RenderEvent {
+                            command_buffer: frame.command_buffer,
+                            render_pass,
+                            camera,
+                            view_projection,
+                            layer_mask: view.layer_mask,
+                            viewport,
+                        });
+                    }
+
+                    SDL_EndGPURenderPass(render_pass);
+
+                    if frame.color_target != frame.swapchain_texture {
+                        it.world().event().entity(flecs::Any).emit(&PostRenderEvent {
+                            command_buffer: frame.command_buffer,
+                            scene_texture: frame.color_target,
+                            swapchain_texture: frame.swapchain_texture,
+                            width: frame.width,
+                            height: frame.height,
+                        });
+                    }
+                }
+
+                SDL_SubmitGPUCommandBuffer(frame.command_buffer);
+                frame.command_buffer = null_mut();
+                frame.swapchain_texture = null_mut();
+                frame.color_target = null_mut();
+            });
+    }
+}
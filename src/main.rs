@@ -1,4 +1,4 @@
-use camera::Camera;
+use camera::{ Camera, CameraClear, CameraView, MainCamera, RenderLayers };
 use flecs_ecs::{
     core::{
         flecs::{ self, pipeline::{ OnStore, OnUpdate, PostUpdate, PreStore, PreUpdate } },
@@ -12,17 +12,38 @@ use flecs_ecs::{
     prelude::*,
 };
 
-use glam::{ Mat4, Vec2, Vec3 };
-use gpu::{ GpuApi, ShadersInitEvent };
-use modules::sprites::{Sprite, SpritesBuffer, SpritesModule};
+use glam::{ Mat4, Vec2, Vec3, Vec4 };
+use events::{ forward_event, EventsModule };
+use gpu::{ GpuApi, GpuModule, ShadersInitEvent };
+use modules::{
+    animation::{ AnimationModule, SpriteAnimation },
+    aseprite::AsepriteSheet,
+    camera2d::{ Camera2D, Camera2DModule },
+    frame_pacing::{ limit_frame_rate, FrameLimiter, FramePacingModule },
+    gamepad::GamepadModule,
+    gltf_import::{ load_gltf, GltfModel },
+    input::{ InputMap, InputModule, Mouse },
+    level::{ ldtk::load_ldtk, tiled::load_tiled, Level, LevelModule },
+    lighting::{ LightOccluder, LightingModule, PointLight },
+    mesh::{ FlyCamera, Material, Mesh, MeshModule, Transform3D },
+    picking::PickingModule,
+    post_process::{ ColorGrading, PostProcessModule, Vignette },
+    replay::{ Replay, ReplayMode, ReplayModule },
+    scaling::{ Scaling, ScalingMode, ScalingModule },
+    sprites::{ Sprite, SpritesBuffer, SpritesModule },
+    text_input::{ TextField, TextFieldSubmitEvent, TextInputFocus, TextInputModule },
+    tilemap::{ TileLayer, TilemapModule, Tileset },
+    time::{ run_frame, FixedTime, FixedUpdate, Time, TimeModule },
+    transform::{ Transform2D, TransformModule },
+};
 use sdl3_sys::{
     self as sdl3,
     error::SDL_GetError,
     gpu::*,
     iostream::SDL_LoadFile,
     pixels::{ SDL_FColor, SDL_PIXELFORMAT_ABGR8888, SDL_PIXELFORMAT_UNKNOWN },
-    scancode::*,
-    stdinc::{ SDL_free, SDL_memcpy, SDL_rand, SDL_strstr },
+    rect::SDL_Rect,
+    stdinc::{ SDL_free, SDL_memcpy, SDL_strstr },
     surface::{ SDL_ConvertSurface, SDL_DestroySurface, SDL_LoadBMP, SDL_Surface },
 };
 use std::{
@@ -33,15 +54,38 @@ use std::{
     u8,
     usize,
 };
+use random::Random;
 use window::Window;
 
 mod camera;
+mod events;
 mod gpu;
 mod window;
 mod modules;
+mod random;
 
 const BASE_PATH: &str = env!("CARGO_MANIFEST_DIR");
 
+/// World units per second.
+const CAMERA_SPEED: f32 = 300.0;
+
+/// Moves an entity around `center` at a fixed rate, `position` is the latest step and
+/// `previous_position` the one before for interpolating between them.
+#[derive(Component, Clone, Copy, Debug)]
+struct Orbit {
+    center: Vec2,
+    radius: f32,
+    /// Radians per second.
+    speed: f32,
+    angle: f32,
+    position: Vec2,
+    previous_position: Vec2,
+}
+
+/// Turns the entity's `Transform2D`, in radians per second.
+#[derive(Component, Clone, Copy, Debug)]
+struct Spin(f32);
+
 #[allow(unused_assignments)]
 pub fn load_shader(
     gpu_device: *mut SDL_GPUDevice,
@@ -173,6 +217,32 @@ impl Uuid {
 fn main() -> Result<(), &'static str> {
     let world = World::new();
 
+    // `--record <file>` saves all input of the session, `--replay <file>` plays it back,
+    // `--model <file>` loads a glTF model from Models/ next to the cube, `--level <file>` a Tiled
+    // map from Levels/ or, with `--level <file.ldtk>:<level>`, an LDtk level, `--sheet <file>`
+    // an Aseprite sheet from Images/ as the sprite texture and plays its first tag on a sprite
+    let args: Vec<String> = std::env::args().collect();
+    let record_path = args
+        .iter()
+        .position(|arg| arg == "--record")
+        .and_then(|index| args.get(index + 1).cloned());
+    let replay_path = args
+        .iter()
+        .position(|arg| arg == "--replay")
+        .and_then(|index| args.get(index + 1).cloned());
+    let model_path = args
+        .iter()
+        .position(|arg| arg == "--model")
+        .and_then(|index| args.get(index + 1).cloned());
+    let level_path = args
+        .iter()
+        .position(|arg| arg == "--level")
+        .and_then(|index| args.get(index + 1).cloned());
+    let sheet_path = args
+        .iter()
+        .position(|arg| arg == "--sheet")
+        .and_then(|index| args.get(index + 1).cloned());
+
     let window_title = "Example window";
 
     unsafe {
@@ -186,7 +256,7 @@ fn main() -> Result<(), &'static str> {
             return Err("Failed to set app metadata");
         }
 
-        if !sdl3::init::SDL_Init(sdl3::init::SDL_INIT_VIDEO) {
+        if !sdl3::init::SDL_Init(sdl3::init::SDL_INIT_VIDEO | sdl3::init::SDL_INIT_GAMEPAD) {
             return Err("Failed to initialize SDL");
         }
     }
@@ -196,13 +266,245 @@ fn main() -> Result<(), &'static str> {
 
 
     let window = Window::new("Example window", 800, 600);
-    let renderer = GpuApi::new(window.0);
+    let mut renderer = GpuApi::new(window.0);
+    renderer.set_sample_count(window.0, 4);
     
     world.set(window);
     world.set(renderer);
-    world.set(Camera::new(0.0, 800.0, 600.0, 0.0, 0.0, -1.0));
     
+    world.import::<TimeModule>();
+    world.import::<FramePacingModule>();
+    world.import::<EventsModule>();
+    world.import::<InputModule>();
+    world.import::<GamepadModule>();
+    world.import::<ReplayModule>();
+    world.import::<TextInputModule>();
+    world.import::<GpuModule>();
+    world.import::<TransformModule>();
+    world.import::<ScalingModule>();
+    world.import::<Camera2DModule>();
+    world.import::<MeshModule>();
+    world.import::<TilemapModule>();
+    world.import::<LevelModule>();
     world.import::<SpritesModule>();
+    world.import::<LightingModule>();
+    world.import::<AnimationModule>();
+    world.import::<PickingModule>();
+    world.import::<PostProcessModule>();
+
+    let main_camera = world
+        .entity_named("main_camera")
+        .set(Camera::new(0.0, 800.0, 600.0, 0.0, 0.0, -1.0))
+        .set(CameraView::default())
+        .set(Vignette::default())
+        .set(Scaling::new(ScalingMode::Letterbox, 800.0, 600.0))
+        .set(Camera2D::new(800.0, 600.0));
+    world.set(MainCamera(main_camera.id()));
+
+    // A warm light circling the middle of the view with a box casting a shadow away from it
+    let light_center = Vec2::new(400.0, 300.0);
+    world
+        .entity_named("light")
+        .set(PointLight::new(light_center, 350.0, Vec3::new(1.0, 0.85, 0.6)))
+        .set(Orbit {
+            center: light_center,
+            radius: 80.0,
+            speed: 1.0,
+            angle: 0.0,
+            position: light_center,
+            previous_position: light_center,
+        });
+    world.entity_named("occluder").set(LightOccluder {
+        position: Vec2::new(280.0, 270.0),
+        size: Vec2::new(40.0, 60.0),
+    });
+
+    // Picture-in-picture view of the 3D props on render layer 2
+    let fly_camera = FlyCamera::new(Vec3::new(0.0, 1.5, -4.0));
+    world
+        .entity_named("fly_camera")
+        .set(Camera::perspective(fly_camera.fov_y, 1.0, 0.1, 100.0))
+        .set(CameraView {
+            clear: CameraClear::Color(0.1, 0.1, 0.15),
+            layer_mask: 2,
+            ..CameraView::new(Vec4::new(0.7, 0.05, 0.25, 0.25), 1)
+        })
+        .set(fly_camera);
+
+    world.get::<&GpuApi>(|gpu_api| {
+        world
+            .entity_named("cube")
+            .set(Mesh::cube(gpu_api.gpu_device))
+            .set(Transform3D::from_translation(Vec3::new(0.0, 0.5, 0.0)))
+            .set(Material {
+                base_color: Vec4::new(0.9, 0.5, 0.2, 1.0),
+                ..Default::default()
+            })
+            .set(RenderLayers(2));
+
+        // Warmer colors for the main view, graded before the vignette
+        match ColorGrading::new("lut_warm.png", gpu_api.gpu_device, 1.0) {
+            Ok(color_grading) => {
+                main_camera.set(color_grading);
+            }
+            Err(error) => println!("Failed to load color grading: {}", error),
+        }
+
+        // Background tiles behind the sprites
+        match Tileset::load(gpu_api.gpu_device, "wall.bmp", Vec2::new(64.0, 64.0)) {
+            Ok(tileset) => {
+                let mut ground = TileLayer::new(tileset, 25, 19, Vec2::new(32.0, 32.0), Vec3::new(0.0, 0.0, -0.5));
+                for y in 0..ground.height() {
+                    for x in 0..ground.width() {
+                        ground.set(x, y, ((x * 7 + y * 3) % tileset.tile_count) + 1);
+                    }
+                }
+                world.entity_named("ground").set(ground);
+            }
+            Err(error) => println!("Failed to load tileset: {}", error),
+        }
+
+        if let Some(path) = &model_path {
+            match load_gltf(&world, gpu_api.gpu_device, path, RenderLayers(2)) {
+                Ok(model) => {
+                    world.entity_from_id(model).get::<&GltfModel>(|model| {
+                        for warning in model.warnings.iter() {
+                            println!("Model {}: {}", path, warning);
+                        }
+                    });
+                }
+                Err(error) => println!("Failed to load model: {}", error),
+            }
+        }
+
+        if let Some(path) = &level_path {
+            let level = match path.split_once(':') {
+                Some((file_name, level)) => load_ldtk(&world, gpu_api.gpu_device, file_name, level),
+                None => load_tiled(&world, gpu_api.gpu_device, path),
+            };
+            match level {
+                Ok(level) => {
+                    world.entity_from_id(level).get::<&Level>(|level| {
+                        for warning in level.warnings.iter() {
+                            println!("Level {}: {}", level.name, warning);
+                        }
+                    });
+                }
+                Err(error) => println!("Failed to load level: {}", error),
+            }
+        }
+
+        // A spinning sprite carrying two smaller ones, they follow it through `Transform2D`
+        world.get::<&mut SpritesBuffer>(|sprites_buffer| {
+            let parent = world
+                .entity_named("spinner")
+                .set(Sprite::new(Vec3::new(0.0, 0.0, -0.2), sprites_buffer))
+                .set(Transform2D::from_translation(Vec2::new(160.0, 140.0)))
+                .set(Spin(1.5))
+                .id();
+
+            let children = [("spinner_left", Vec2::new(-60.0, 0.0)), ("spinner_right", Vec2::new(60.0, 0.0))];
+            for (name, translation) in children {
+                let mut sprite = Sprite::new(Vec3::new(0.0, 0.0, -0.1), sprites_buffer);
+                sprite.scale = Vec2::splat(16.0);
+                world
+                    .entity_named(name)
+                    .child_of_id(parent)
+                    .set(sprite)
+                    .set(Transform2D::from_translation(translation));
+            }
+        });
+
+        if let Some(path) = &sheet_path {
+            match AsepriteSheet::load(gpu_api.gpu_device, path) {
+                Ok(sheet) => {
+                    for warning in sheet.warnings.iter() {
+                        println!("Sheet {}: {}", path, warning);
+                    }
+                    world.get::<&mut SpritesBuffer>(|sprites_buffer| {
+                        sprites_buffer.set_texture(gpu_api.gpu_device, sheet.texture);
+                        spawn_sheet_sprite(&world, sprites_buffer, &sheet);
+                    });
+                }
+                Err(error) => println!("Failed to load sprite sheet: {}", error),
+            }
+        }
+    });
+
+    let replay = match (&replay_path, &record_path) {
+        (Some(path), _) =>
+            match Replay::load(path) {
+                Ok(replay) => replay,
+                Err(error) => {
+                    println!("Failed to load replay: {}", error);
+                    return Err("Failed to load replay");
+                }
+            }
+        (None, Some(_)) => Replay::record(Random::from_time().seed),
+        (None, None) => Replay::off(),
+    };
+    let random = match replay.mode {
+        ReplayMode::Off => Random::from_time(),
+        _ => Random::new(replay.seed),
+    };
+    world.set(random);
+    world.set(replay);
+
+    match InputMap::load("input.cfg") {
+        Ok(input_map) => {
+            world.set(input_map);
+        }
+        Err(error) => println!("Failed to load input bindings: {}", error),
+    }
+
+    // A text field along the bottom of the window, the "type" action starts a new entry in it.
+    // Nothing draws text yet, the IME shows its candidates there and the entry is printed.
+    let text_field = world
+        .entity_named("text_field")
+        .set(TextField {
+            max_length: Some(32),
+            area: Some(SDL_Rect { x: 16, y: 560, w: 320, h: 24 }),
+            char_width: 10.0,
+            ..TextField::new("")
+        })
+        .id();
+
+    // Reads the event's copy of the text, the module still borrows the field while emitting it
+    observer!("submit_text_field", world, TextFieldSubmitEvent, flecs::Any).each_iter(|it, _, _| {
+        let event = &*it.param();
+        println!("Submitted: {}", event.text);
+        it.world().get::<&mut TextInputFocus>(|focus| focus.clear());
+    });
+
+    system!("orbit", world, &FixedTime($), &mut Orbit)
+        .kind::<FixedUpdate>()
+        .each(|(fixed_time, orbit)| {
+            orbit.angle += orbit.speed * fixed_time.step;
+            orbit.previous_position = orbit.position;
+            orbit.position = orbit.center + Vec2::from_angle(orbit.angle) * orbit.radius;
+        });
+
+    // The light is drawn between the last two fixed steps so it moves smoothly at any frame rate
+    system!("interpolate_orbiting_light", world, &FixedTime($), &Orbit, &mut PointLight)
+        .kind::<OnUpdate>()
+        .each(|(fixed_time, orbit, light)| {
+            light.position = orbit.previous_position.lerp(orbit.position, fixed_time.alpha);
+        });
+
+    system!("spin", world, &Time($), &Spin, &mut Transform2D)
+        .kind::<OnUpdate>()
+        .each(|(time, spin, transform)| {
+            transform.rotation += spin.0 * time.delta;
+        });
+
+    system!("camera_controls", world, &Time($), &InputMap($), &Mouse($), &mut Camera2D)
+        .kind::<OnUpdate>()
+        .each(|(time, input, mouse, camera)| {
+            // Window y grows downwards, the "move" action's y points up
+            let movement = input.axis2d("move") * Vec2::new(1.0, -1.0);
+            camera.position += (movement * CAMERA_SPEED * time.delta) / camera.zoom;
+            camera.zoom = (camera.zoom * (1.1f32).powf(mouse.wheel.y)).clamp(0.25, 8.0);
+        });
 
     // init the renderer get the world and the window
     world.get::<&GpuApi>(|renderer| {
@@ -215,13 +517,17 @@ fn main() -> Result<(), &'static str> {
     let mut event = sdl3::events::SDL_Event::default();
 
     let mut count = 0;
+    world.set(FrameLimiter::new(144.0));
+
+    let mut last_frame = Instant::now();
     'running: loop {
+        let frame_start = unsafe { sdl3::timer::SDL_GetTicksNS() };
+
         while (unsafe { sdl3::events::SDL_PollEvent(&mut event) }) {
-            match sdl3::events::SDL_EventType(unsafe { event.r#type }) {
-                sdl3::events::SDL_EventType::QUIT => {
-                    break 'running;
-                }
-                _ => {}
+            forward_event(&world, &event);
+
+            if sdl3::events::SDL_EventType(unsafe { event.r#type }) == sdl3::events::SDL_EventType::QUIT {
+                break 'running;
             }
         }
 
@@ -229,51 +535,57 @@ fn main() -> Result<(), &'static str> {
             sdl3::events::SDL_PumpEvents();
         }
 
-        let mut numkeys: c_int = 0;
-        let key_state_ptr = unsafe { sdl3::keyboard::SDL_GetKeyboardState(&mut numkeys) };
-
-        // Convert the raw pointer into a slice.
-        let key_states: &[bool] = unsafe {
-            std::slice::from_raw_parts(key_state_ptr, numkeys as usize)
-        };
-
-        // For example, if you're using a world to store a Camera:
-        if key_states[SDL_SCANCODE_W.0 as usize] {
-            world.get::<&mut Camera>(|camera| {
-                camera.translate(glam::Vec3::Y);
-            });
-        }
-
-        if key_states[SDL_SCANCODE_A.0 as usize] {
-            world.get::<&mut Camera>(|camera| {
-                camera.translate(glam::Vec3::X);
-            });
-        }
-
-        if key_states[SDL_SCANCODE_S.0 as usize] {
-            world.get::<&mut Camera>(|camera| {
-                camera.translate(glam::Vec3::NEG_Y);
-            });
-        }
+        let mut spawn = false;
+        let mut start_typing = false;
+        world.get::<&InputMap>(|input| {
+            spawn = input.pressed("spawn");
+            start_typing = input.just_pressed("type");
+        });
 
-        if key_states[SDL_SCANCODE_D.0 as usize] {
-            world.get::<&mut Camera>(|camera| {
-                camera.translate(glam::Vec3::NEG_X);
+        if start_typing {
+            world.entity_from_id(text_field).get::<&mut TextField>(|field| {
+                field.text.clear();
+                field.cursor = 0;
             });
+            world.get::<&mut TextInputFocus>(|focus| focus.focus(text_field));
+            println!("Typing into the text field, Return submits and Escape stops");
         }
 
-        if key_states[SDL_SCANCODE_P.0 as usize] {
+        if spawn {
             // For example, spawn sprites
             count += 100;
-            world.get::<&mut SpritesBuffer>(|sprites_buffer| {
+            world.get::<(&mut SpritesBuffer, &mut Random)>(|(sprites_buffer, random)| {
                 for _ in 0..100 {
-                    spawn_sprite(&world, sprites_buffer);
+                    spawn_sprite(&world, sprites_buffer, random);
                 }
             });
             println!("{}", count);
         }
 
-        world.progress();
+        let mut replay_delta_time = None;
+        world.get::<&Replay>(|replay| {
+            replay_delta_time = replay.next_delta_time();
+        });
+
+        let now = Instant::now();
+        let frame_time = (now - last_frame).as_secs_f32();
+        last_frame = now;
+
+        // `world.quit()` was called
+        if !run_frame(&world, replay_delta_time.unwrap_or(frame_time)) {
+            break 'running;
+        }
+
+        limit_frame_rate(&world, frame_start);
+    }
+
+    if let Some(path) = &record_path {
+        world.get::<&Replay>(|replay| {
+            match replay.save(path) {
+                Ok(()) => println!("Saved {} frames of input to {}", replay.frames.len(), path),
+                Err(error) => println!("Failed to save input recording: {}", error),
+            }
+        });
     }
 
     unsafe {
@@ -283,13 +595,27 @@ fn main() -> Result<(), &'static str> {
     Ok(())
 }
 
-fn spawn_sprite(world: &World, sprite_buffer: &mut SpritesBuffer) {
-    unsafe {
-        let x = SDL_rand(800) as f32;
-        let y = SDL_rand(600) as f32;
-        world
-            .entity()
-            .set(Uuid::new())
-            .set(Sprite::new(Vec3::new(x, y, 0.0), sprite_buffer));
+/// Spawns a sprite in the middle of the view playing the sheet's first tag.
+fn spawn_sheet_sprite(world: &World, sprite_buffer: &mut SpritesBuffer, sheet: &AsepriteSheet) {
+    let Some(frame) = sheet.frames.first() else {
+        return;
+    };
+
+    let mut sprite = Sprite::new(Vec3::new(400.0, 300.0, -0.1), sprite_buffer);
+    sprite.texture = frame.rect;
+    sprite.scale = sheet.pixel_size(&frame.rect) * 4.0;
+
+    let entity = world.entity_named("sheet_sprite").set(sprite);
+    if let Some(clip) = sheet.tags.first() {
+        entity.set(SpriteAnimation::new(sheet.clips.clone(), clip));
     }
 }
+
+fn spawn_sprite(world: &World, sprite_buffer: &mut SpritesBuffer, random: &mut Random) {
+    let x = random.range(800) as f32;
+    let y = random.range(600) as f32;
+    world
+        .entity()
+        .set(Uuid::new())
+        .set(Sprite::new(Vec3::new(x, y, 0.0), sprite_buffer));
+}
@@ -1 +1,19 @@
-pub mod sprites;
\ No newline at end of file
+pub mod animation;
+pub mod aseprite;
+pub mod camera2d;
+pub mod frame_pacing;
+pub mod gamepad;
+pub mod gltf_import;
+pub mod input;
+pub mod level;
+pub mod lighting;
+pub mod mesh;
+pub mod picking;
+pub mod post_process;
+pub mod replay;
+pub mod scaling;
+pub mod sprites;
+pub mod text_input;
+pub mod tilemap;
+pub mod time;
+pub mod transform;
@@ -0,0 +1,268 @@
+use std::{ collections::HashMap, sync::Arc };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PostUpdate }, Entity, World },
+    macros::{ system, Component },
+    prelude::{ Module, SystemAPI },
+};
+
+use crate::modules::{ sprites::{ Sprite, Texture }, time::Time };
+
+/// Plays `SpriteAnimation` clips at `PostUpdate` by writing the current frame's rect to
+/// `Sprite::texture`, emitting `AnimationEndEvent` and `AnimationFrameEvent` along the way.
+/// Animations follow `Time::delta`, so they slow down with the time scale and stop when paused.
+#[derive(Component)]
+pub struct AnimationModule;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationMode {
+    #[default]
+    Loop,
+    /// Plays forwards then backwards, without repeating the first and last frames.
+    PingPong,
+    /// Stops on the last frame.
+    Once,
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationFrame {
+    /// Area of the sprite texture, see `Sprite::texture`.
+    pub rect: Texture,
+    /// In seconds.
+    pub duration: f32,
+    /// Emits an `AnimationFrameEvent` every time playback moves onto the frame, e.g. for
+    /// footsteps.
+    pub tag: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: AnimationMode,
+}
+
+/// Clips by name, shared between all entities using them.
+pub type AnimationClips = Arc<HashMap<String, AnimationClip>>;
+
+#[derive(Component, Clone, Debug)]
+pub struct SpriteAnimation {
+    pub clips: AnimationClips,
+    /// Name of the playing clip.
+    pub clip: String,
+    pub frame: usize,
+    /// Time spent on the current frame.
+    pub elapsed: f32,
+    /// Playback rate, 2.0 plays twice as fast.
+    pub speed: f32,
+    pub playing: bool,
+    /// 1 or -1, only goes backwards in `AnimationMode::PingPong`.
+    direction: i32,
+}
+
+/// Emitted when a clip reaches its end: every cycle for `Loop` and `PingPong` clips and once
+/// when a `Once` clip stops.
+#[derive(Component, Clone, Debug)]
+pub struct AnimationEndEvent {
+    pub entity: Entity,
+    pub clip: String,
+}
+
+/// Emitted when playback moves onto a frame with a tag.
+#[derive(Component, Clone, Debug)]
+pub struct AnimationFrameEvent {
+    pub entity: Entity,
+    pub clip: String,
+    pub frame: usize,
+    pub tag: String,
+}
+
+/// Frames are never shorter than this, so zero durations can not stall playback.
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+impl AnimationClip {
+    pub fn new(frames: Vec<AnimationFrame>, mode: AnimationMode) -> Self {
+        Self { frames, mode }
+    }
+
+    /// A clip from `count` cells of a sheet split into `columns` by `rows` equal cells, starting
+    /// at cell `first` and counting left to right, top to bottom.
+    pub fn from_grid(
+        columns: u32,
+        rows: u32,
+        first: u32,
+        count: u32,
+        frame_duration: f32,
+        mode: AnimationMode
+    ) -> Self {
+        let (w, h) = (1.0 / (columns as f32), 1.0 / (rows as f32));
+        let frames = (first..first + count)
+            .map(|cell| AnimationFrame {
+                rect: Texture {
+                    u: ((cell % columns) as f32) * w,
+                    v: ((cell / columns) as f32) * h,
+                    w,
+                    h,
+                },
+                duration: frame_duration,
+                tag: None,
+            })
+            .collect();
+
+        Self { frames, mode }
+    }
+
+    /// Tags frame `index` if it exists.
+    pub fn with_tag(mut self, index: usize, tag: &str) -> Self {
+        if let Some(frame) = self.frames.get_mut(index) {
+            frame.tag = Some(tag.to_owned());
+        }
+        self
+    }
+
+    /// Length of one cycle in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+impl SpriteAnimation {
+    pub fn new(clips: AnimationClips, clip: &str) -> Self {
+        Self {
+            clips,
+            clip: clip.to_owned(),
+            frame: 0,
+            elapsed: 0.0,
+            speed: 1.0,
+            playing: true,
+            direction: 1,
+        }
+    }
+
+    /// Switches to `clip` from its first frame, keeps playing if it is already the current one.
+    /// Returns false when there is no clip with that name.
+    pub fn play(&mut self, clip: &str) -> bool {
+        if !self.clips.contains_key(clip) {
+            return false;
+        }
+
+        if self.clip != clip || !self.playing {
+            self.clip = clip.to_owned();
+            self.restart();
+        }
+        true
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.direction = 1;
+        self.playing = true;
+    }
+
+    pub fn current_clip(&self) -> Option<&AnimationClip> {
+        self.clips.get(&self.clip)
+    }
+
+    pub fn current_frame(&self) -> Option<&AnimationFrame> {
+        self.current_clip().and_then(|clip| clip.frames.get(self.frame))
+    }
+
+    pub fn finished(&self) -> bool {
+        !self.playing
+    }
+
+    /// Moves to the next frame, returns true when that ended a cycle.
+    fn step(&mut self, clip: &AnimationClip) -> bool {
+        let last = clip.frames.len() - 1;
+
+        match clip.mode {
+            AnimationMode::Loop => {
+                if self.frame >= last {
+                    self.frame = 0;
+                    return true;
+                }
+                self.frame += 1;
+            }
+            AnimationMode::Once => {
+                if self.frame >= last {
+                    self.playing = false;
+                    self.elapsed = 0.0;
+                    return true;
+                }
+                self.frame += 1;
+            }
+            AnimationMode::PingPong => {
+                if last == 0 {
+                    return true;
+                }
+                if (self.direction > 0 && self.frame >= last) || (self.direction < 0 && self.frame == 0) {
+                    self.direction = -self.direction;
+                }
+                self.frame = (self.frame as i32 + self.direction) as usize;
+                return self.frame == 0;
+            }
+        }
+        false
+    }
+}
+
+impl Module for AnimationModule {
+    fn module(world: &World) {
+        world.component::<SpriteAnimation>();
+        world.component::<AnimationEndEvent>();
+        world.component::<AnimationFrameEvent>();
+
+        system!("animate_sprites", world, &Time($), &mut SpriteAnimation, &mut Sprite)
+            .kind::<PostUpdate>()
+            .each_iter(|it, index, (time, animation, sprite)| {
+                let world = it.world();
+                let entity = it.entity(index).id();
+
+                let clips = animation.clips.clone();
+                let Some(clip) = clips.get(&animation.clip).filter(|clip| !clip.frames.is_empty()) else {
+                    return;
+                };
+                animation.frame = animation.frame.min(clip.frames.len() - 1);
+
+                let mut ended = false;
+                let mut tagged = Vec::new();
+                if animation.playing {
+                    animation.elapsed += time.delta * animation.speed;
+
+                    while animation.playing {
+                        let duration = clip.frames[animation.frame].duration.max(MIN_FRAME_DURATION);
+                        if animation.elapsed < duration {
+                            break;
+                        }
+
+                        animation.elapsed -= duration;
+                        ended |= animation.step(clip);
+                        // A stopped `Once` clip stays on its last frame without entering it again
+                        if !animation.playing {
+                            break;
+                        }
+                        if let Some(tag) = &clip.frames[animation.frame].tag {
+                            tagged.push((animation.frame, tag.clone()));
+                        }
+                    }
+                }
+
+                sprite.texture = clip.frames[animation.frame].rect;
+
+                for (frame, tag) in tagged {
+                    world.event().entity(flecs::Any).emit(&AnimationFrameEvent {
+                        entity,
+                        clip: animation.clip.clone(),
+                        frame,
+                        tag,
+                    });
+                }
+                if ended {
+                    world.event().entity(flecs::Any).emit(&AnimationEndEvent {
+                        entity,
+                        clip: animation.clip.clone(),
+                    });
+                }
+            });
+    }
+}
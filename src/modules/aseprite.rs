@@ -0,0 +1,180 @@
+use std::{ collections::HashMap, path::{ Component, Path }, ptr::null_mut, sync::Arc };
+
+use glam::Vec2;
+use sdl3_sys::gpu::{ SDL_GPUDevice, SDL_GPUTexture };
+use serde_json::Value;
+
+use crate::{
+    gpu::upload_rgba,
+    modules::{
+        animation::{ AnimationClip, AnimationClips, AnimationFrame, AnimationMode },
+        sprites::Texture,
+    },
+    BASE_PATH,
+};
+
+/// A sprite sheet exported from Aseprite as JSON + PNG (`File > Export Sprite Sheet` or
+/// `aseprite -b --sheet sheet.png --data sheet.json --list-tags --list-slices`), with the atlas
+/// uploaded and every rect converted to `Sprite::texture` coordinates.
+///
+/// Both the "Hash" and "Array" JSON layouts are read. Rotated frames are not supported and
+/// trimmed frames keep their trimmed rect, so animations should be exported untrimmed.
+pub struct AsepriteSheet {
+    pub texture: *mut SDL_GPUTexture,
+    /// Atlas size in pixels.
+    pub size: Vec2,
+    /// Every exported frame in order, with its duration.
+    pub frames: Vec<AnimationFrame>,
+    /// One clip per tag, ready for `SpriteAnimation::new`.
+    pub clips: AnimationClips,
+    /// Names of the loaded tags in the order of the file.
+    pub tags: Vec<String>,
+    /// Slice bounds on their first key's frame, by slice name.
+    pub slices: HashMap<String, Texture>,
+    /// Tags and slices that could not be loaded but did not stop the import.
+    pub warnings: Vec<String>,
+}
+
+unsafe impl Send for AsepriteSheet {}
+unsafe impl Sync for AsepriteSheet {}
+
+/// Reads an Aseprite `{ x, y, w, h }` object in pixels.
+fn read_rect(value: &Value) -> Option<(f32, f32, f32, f32)> {
+    Some((
+        value["x"].as_f64()? as f32,
+        value["y"].as_f64()? as f32,
+        value["w"].as_f64()? as f32,
+        value["h"].as_f64()? as f32,
+    ))
+}
+
+impl AsepriteSheet {
+    /// Loads `Images/<file_name>`, the JSON data file. The atlas image is looked up next to it
+    /// under the relative path in `meta.image`, which may not leave that directory.
+    pub fn load(gpu_device: *mut SDL_GPUDevice, file_name: &str) -> Result<Self, String> {
+        let path = format!("{}/Images/{}", BASE_PATH, file_name);
+        let text = std::fs::read_to_string(&path).map_err(|error| format!("{}: {}", path, error))?;
+        let json: Value = serde_json::from_str(&text).map_err(|error| format!("{}: {}", path, error))?;
+        let meta = &json["meta"];
+
+        let image_name = meta["image"]
+            .as_str()
+            .ok_or_else(|| format!("{}: meta.image is missing", path))?;
+        let below_sheet = Path::new(image_name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !below_sheet {
+            return Err(format!("{}: meta.image {} is not a relative path below the sheet", path, image_name));
+        }
+        let image_path = Path::new(&path).with_file_name(image_name);
+        let image = image::open(&image_path)
+            .map_err(|error| format!("{}: {}", image_path.display(), error))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return Err(format!("{}: the atlas is empty", image_path.display()));
+        }
+        let size = Vec2::new(width as f32, height as f32);
+
+        let to_texture = |(x, y, w, h): (f32, f32, f32, f32)| Texture {
+            u: x / size.x,
+            v: y / size.y,
+            w: w / size.x,
+            h: h / size.y,
+        };
+
+        // "Hash" exports key the frames by file name, "Array" exports list them
+        let frame_values: Vec<&Value> = match &json["frames"] {
+            Value::Object(frames) => frames.values().collect(),
+            Value::Array(frames) => frames.iter().collect(),
+            _ => {
+                return Err(format!("{}: frames is missing", path));
+            }
+        };
+
+        let mut frames = Vec::with_capacity(frame_values.len());
+        // Atlas position of each frame's untrimmed top left corner, slices are relative to it
+        let mut origins = Vec::with_capacity(frame_values.len());
+        for value in frame_values {
+            if value["rotated"].as_bool() == Some(true) {
+                return Err(format!("{}: rotated frames are not supported", path));
+            }
+
+            let rect = read_rect(&value["frame"]).ok_or_else(|| format!("{}: invalid frame", path))?;
+            let (trim_x, trim_y, _, _) = read_rect(&value["spriteSourceSize"]).unwrap_or_default();
+            frames.push(AnimationFrame {
+                rect: to_texture(rect),
+                duration: (value["duration"].as_f64().unwrap_or(100.0) / 1000.0) as f32,
+                tag: None,
+            });
+            origins.push(Vec2::new(rect.0 - trim_x, rect.1 - trim_y));
+        }
+
+        let mut warnings = Vec::new();
+        let mut clips = HashMap::new();
+        let mut tags = Vec::new();
+        for tag in meta["frameTags"].as_array().into_iter().flatten() {
+            let name = tag["name"].as_str().unwrap_or_default();
+            let (Some(from), Some(to)) = (tag["from"].as_u64(), tag["to"].as_u64()) else {
+                warnings.push(format!("tag {} has no frame range", name));
+                continue;
+            };
+            let (from, to) = (from as usize, to as usize);
+            if from > to || to >= frames.len() {
+                warnings.push(format!("tag {} has invalid frames {}..={}", name, from, to));
+                continue;
+            }
+
+            let mut clip_frames = frames[from..=to].to_vec();
+            let direction = tag["direction"].as_str().unwrap_or("forward");
+            if direction == "reverse" || direction == "pingpong_reverse" {
+                clip_frames.reverse();
+            }
+
+            // Newer versions export the repeat count as a string, tags played once become `Once`
+            let repeat = tag["repeat"].as_str().and_then(|repeat| repeat.parse::<u32>().ok());
+            let mode = match direction {
+                "pingpong" | "pingpong_reverse" => AnimationMode::PingPong,
+                _ if repeat == Some(1) => AnimationMode::Once,
+                _ => AnimationMode::Loop,
+            };
+
+            if clips.insert(name.to_owned(), AnimationClip::new(clip_frames, mode)).is_none() {
+                tags.push(name.to_owned());
+            }
+        }
+
+        let mut slices = HashMap::new();
+        for slice in meta["slices"].as_array().into_iter().flatten() {
+            let name = slice["name"].as_str().unwrap_or_default();
+            let key = &slice["keys"][0];
+            let frame = key["frame"].as_u64().unwrap_or(0) as usize;
+            let (Some(origin), Some((x, y, w, h))) = (origins.get(frame), read_rect(&key["bounds"])) else {
+                warnings.push(format!("slice {} has no bounds on an exported frame", name));
+                continue;
+            };
+
+            slices.insert(name.to_owned(), to_texture((origin.x + x, origin.y + y, w, h)));
+        }
+
+        let texture = upload_rgba(gpu_device, width, height, image.as_raw());
+        if texture == null_mut() {
+            return Err(format!("{}: failed to upload the atlas", image_path.display()));
+        }
+
+        Ok(Self {
+            texture,
+            size,
+            frames,
+            clips: Arc::new(clips),
+            tags,
+            slices,
+            warnings,
+        })
+    }
+
+    /// Size in pixels of a rect from this sheet, e.g. for `Sprite::scale`.
+    pub fn pixel_size(&self, rect: &Texture) -> Vec2 {
+        Vec2::new(rect.w, rect.h) * self.size
+    }
+}
@@ -0,0 +1,222 @@
+use flecs_ecs::{
+    core::{ flecs::pipeline::PostUpdate, Entity, World, WorldGet },
+    macros::{ system, Component },
+    prelude::{ Module, SystemAPI },
+};
+use glam::{ Mat4, Vec2, Vec3, Vec4 };
+
+use crate::{ camera::Camera, modules::{ sprites::Sprite, time::Time } };
+
+/// Rebuilds the view and projection of every entity with `Camera` and `Camera2D` at
+/// `PostUpdate`, after `ScalingModule` updated `Camera2D::size` and `Camera2D::viewport`.
+/// `CameraFollow`, `CameraBounds` and `CameraShake` on the same entity are applied in that
+/// order first.
+#[derive(Component)]
+pub struct Camera2DModule;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Camera2D {
+    /// World position shown at the center of the viewport.
+    pub position: Vec2,
+    /// Values above 1 show less of the world.
+    pub zoom: f32,
+    /// Counter-clockwise, in radians.
+    pub rotation: f32,
+    /// World size visible at zoom 1.
+    pub size: Vec2,
+    /// Window area drawn to in pixels as `(x, y, width, height)`, kept in sync with
+    /// `CameraView::viewport` by `ScalingModule`.
+    pub viewport: Option<Vec4>,
+}
+
+/// Moves the camera towards the center of the target's `Sprite`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CameraFollow {
+    pub target: Entity,
+    pub offset: Vec2,
+    /// Half extents around the camera position the target can move in without the camera
+    /// following.
+    pub dead_zone: Vec2,
+    /// How fast the camera catches up, roughly the fraction of the distance per 1/60th of a
+    /// second. `0.0` snaps to the target.
+    pub smoothing: f32,
+}
+
+/// World area the camera view stays inside.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CameraBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// Trauma based shake, add trauma on impacts and it decays by itself.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CameraShake {
+    /// 0..1, the shake strength is `trauma * trauma`.
+    pub trauma: f32,
+    /// Trauma lost per second.
+    pub decay: f32,
+    pub max_offset: Vec2,
+    /// In radians.
+    pub max_angle: f32,
+    /// Shake oscillations per second.
+    pub frequency: f32,
+}
+
+impl Camera2D {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            position: Vec2::new(width, height) * 0.5,
+            zoom: 1.0,
+            rotation: 0.0,
+            size: Vec2::new(width, height),
+            viewport: None,
+        }
+    }
+
+    /// World size visible with the current zoom.
+    pub fn visible_size(&self) -> Vec2 {
+        self.size / self.zoom.max(f32::EPSILON)
+    }
+
+    pub fn view(&self, offset: Vec2, angle: f32) -> Mat4 {
+        Mat4::from_rotation_z(-(self.rotation + angle)) *
+            Mat4::from_translation(-(self.position + offset).extend(0.0))
+    }
+
+    /// Centered on the view, `y` grows downwards like window coordinates.
+    pub fn projection(&self, near: f32, far: f32) -> Mat4 {
+        let half = self.visible_size() * 0.5;
+        Mat4::orthographic_lh(-half.x, half.x, half.y, -half.y, near, far)
+    }
+}
+
+impl CameraFollow {
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            offset: Vec2::ZERO,
+            dead_zone: Vec2::ZERO,
+            smoothing: 0.1,
+        }
+    }
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            decay: 1.5,
+            max_offset: Vec2::new(16.0, 16.0),
+            max_angle: 0.05,
+            frequency: 25.0,
+        }
+    }
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Offset and angle for `time` seconds, smooth enough to not look like noise every frame.
+    fn sample(&self, time: f32) -> (Vec2, f32) {
+        let strength = self.trauma * self.trauma;
+        let phase = time * self.frequency;
+        let wave = |seed: f32| (phase * (1.0 + seed * 0.37) + seed * 12.9898).sin();
+
+        (Vec2::new(wave(1.0), wave(2.0)) * self.max_offset * strength, wave(3.0) * self.max_angle * strength)
+    }
+}
+
+/// Zeroes `offset` inside `dead_zone`, the rest of it is how far the edge was crossed.
+fn apply_dead_zone(offset: Vec2, dead_zone: Vec2) -> Vec2 {
+    let axis = |value: f32, zone: f32| {
+        if value.abs() <= zone { 0.0 } else { value - zone * value.signum() }
+    };
+
+    Vec2::new(axis(offset.x, dead_zone.x), axis(offset.y, dead_zone.y))
+}
+
+/// Clamps the center so the visible area stays inside the bounds, or centers it on an axis
+/// where the bounds are smaller than the view.
+fn clamp_to_bounds(position: Vec2, half_size: Vec2, bounds: &CameraBounds) -> Vec2 {
+    let axis = |value: f32, half: f32, min: f32, max: f32| {
+        if max - min <= half * 2.0 { (min + max) * 0.5 } else { value.clamp(min + half, max - half) }
+    };
+
+    Vec2::new(
+        axis(position.x, half_size.x, bounds.min.x, bounds.max.x),
+        axis(position.y, half_size.y, bounds.min.y, bounds.max.y)
+    )
+}
+
+impl Module for Camera2DModule {
+    fn module(world: &World) {
+        world.component::<Camera2D>();
+        world.component::<CameraFollow>();
+        world.component::<CameraBounds>();
+        world.component::<CameraShake>();
+
+        system!(
+            "update_camera2d",
+            world,
+            &Time($),
+            &mut Camera,
+            &mut Camera2D,
+            ?&CameraFollow,
+            ?&CameraBounds,
+            ?&mut CameraShake
+        )
+            .kind::<PostUpdate>()
+            .each_iter(|it, _, (time, camera, camera2d, follow, bounds, shake)| {
+                let world = it.world();
+
+                if let Some(follow) = follow {
+                    let target = world.entity_from_id(follow.target);
+                    let mut target_position = None;
+                    if target.is_alive() {
+                        target.get::<Option<&Sprite>>(|sprite| {
+                            target_position = sprite.map(|sprite| {
+                                sprite.position.truncate() + sprite.scale * 0.5
+                            });
+                        });
+                    }
+
+                    if let Some(target_position) = target_position {
+                        let offset = apply_dead_zone(
+                            target_position + follow.offset - camera2d.position,
+                            follow.dead_zone
+                        );
+                        // Frame rate independent exponential smoothing
+                        let amount = if follow.smoothing <= 0.0 {
+                            1.0
+                        } else {
+                            1.0 - (1.0 - follow.smoothing.min(1.0)).powf(time.delta * 60.0)
+                        };
+                        camera2d.position += offset * amount;
+                    }
+                }
+
+                if let Some(bounds) = bounds {
+                    camera2d.position = clamp_to_bounds(
+                        camera2d.position,
+                        camera2d.visible_size() * 0.5,
+                        bounds
+                    );
+                }
+
+                let (shake_offset, shake_angle) = match shake {
+                    Some(shake) => {
+                        shake.trauma = (shake.trauma - shake.decay * time.delta).max(0.0);
+                        shake.sample(time.elapsed as f32)
+                    }
+                    None => (Vec2::ZERO, 0.0),
+                };
+
+                camera.projection = camera2d.projection(camera.near, camera.far);
+                camera.view = camera2d.view(shake_offset, shake_angle);
+                camera.view_projection = camera.projection * camera.view;
+            });
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::OnLoad }, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, SystemAPI },
+};
+use sdl3_sys::timer::{ SDL_DelayPrecise, SDL_GetTicksNS };
+
+use crate::events::{ WindowEvent, WindowEventKind };
+
+/// Caps the frame rate with `FrameLimiter` and keeps `FrameStats` up to date. The main loop
+/// records `SDL_GetTicksNS()` when a frame starts and calls `limit_frame_rate` when it ends.
+#[derive(Component)]
+pub struct FramePacingModule;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FrameLimiter {
+    /// Frames per second while the window is in use, `None` runs uncapped.
+    pub target_fps: Option<f32>,
+    /// Cap while the window is unfocused or minimized, `None` uses `target_fps`.
+    pub background_fps: Option<f32>,
+    pub focused: bool,
+    pub minimized: bool,
+}
+
+/// Frame times in seconds over the last `FrameStats::SAMPLE_COUNT` frames.
+#[derive(Component, Clone, Debug, Default)]
+pub struct FrameStats {
+    pub samples: VecDeque<f32>,
+    pub min: f32,
+    pub average: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self {
+            target_fps: None,
+            background_fps: Some(10.0),
+            focused: true,
+            minimized: false,
+        }
+    }
+}
+
+impl FrameLimiter {
+    pub fn new(target_fps: f32) -> Self {
+        Self {
+            target_fps: Some(target_fps),
+            ..Default::default()
+        }
+    }
+
+    /// The cap that applies right now.
+    pub fn current_fps(&self) -> Option<f32> {
+        if !self.focused || self.minimized {
+            self.background_fps.or(self.target_fps)
+        } else {
+            self.target_fps
+        }
+    }
+}
+
+impl FrameStats {
+    pub const SAMPLE_COUNT: usize = 240;
+
+    pub fn push(&mut self, frame_time: f32) {
+        if self.samples.len() == Self::SAMPLE_COUNT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        sorted.sort_unstable_by(f32::total_cmp);
+
+        let percentile = |p: f32| sorted[(((sorted.len() - 1) as f32) * p).round() as usize];
+
+        self.min = sorted[0];
+        self.max = sorted[sorted.len() - 1];
+        self.average = sorted.iter().sum::<f32>() / (sorted.len() as f32);
+        self.p50 = percentile(0.5);
+        self.p95 = percentile(0.95);
+        self.p99 = percentile(0.99);
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.average > 0.0 { 1.0 / self.average } else { 0.0 }
+    }
+}
+
+/// Sleeps until the frame that started at `frame_start` (from `SDL_GetTicksNS`) has taken as
+/// long as the current `FrameLimiter` cap allows.
+pub fn limit_frame_rate(world: &World, frame_start: u64) {
+    let mut fps = None;
+    world.get::<&FrameLimiter>(|limiter| {
+        fps = limiter.current_fps();
+    });
+
+    let Some(fps) = fps.filter(|fps| *fps > 0.0) else {
+        return;
+    };
+
+    let frame_duration = (1_000_000_000.0 / fps) as u64;
+    unsafe {
+        let elapsed = SDL_GetTicksNS().saturating_sub(frame_start);
+        if elapsed < frame_duration {
+            SDL_DelayPrecise(frame_duration - elapsed);
+        }
+    }
+}
+
+impl Module for FramePacingModule {
+    fn module(world: &World) {
+        world.component::<FrameLimiter>();
+        world.component::<FrameStats>();
+
+        world.set(FrameLimiter::default());
+        world.set(FrameStats::default());
+
+        observer!("frame_limiter_window", world, WindowEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            it.world().get::<&mut FrameLimiter>(|limiter| {
+                match event.kind {
+                    WindowEventKind::FocusGained => {
+                        limiter.focused = true;
+                    }
+                    WindowEventKind::FocusLost => {
+                        limiter.focused = false;
+                    }
+                    WindowEventKind::Minimized | WindowEventKind::Hidden => {
+                        limiter.minimized = true;
+                    }
+                    WindowEventKind::Restored | WindowEventKind::Shown => {
+                        limiter.minimized = false;
+                    }
+                    _ => {}
+                }
+            });
+        });
+
+        system!("frame_stats", world, &mut FrameStats($))
+            .kind::<OnLoad>()
+            .each_iter(|it, _, stats| {
+                // The frame time flecs was given, before clamping and time scale
+                if it.delta_time() > 0.0 {
+                    stats.push(it.delta_time());
+                }
+            });
+    }
+}
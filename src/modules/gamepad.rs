@@ -0,0 +1,435 @@
+use std::{ ffi::{ c_char, CStr }, os::raw::c_int, ptr::null_mut };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::OnLoad }, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Builder, Module, QueryAPI, QueryBuilderImpl, SystemAPI },
+};
+use glam::Vec2;
+use sdl3_sys::{ error::SDL_GetError, gamepad::*, joystick::* };
+
+use crate::{
+    events::{ GamepadDeviceEvent, GamepadDeviceEventKind, JoystickDeviceEvent, JoystickDeviceEventKind },
+    modules::input::{ InputState, JOYSTICK_AXES },
+};
+
+/// Opens gamepads as they are connected and gives each one an entity with a `Gamepad`
+/// component, destroyed again when it is disconnected. Joysticks SDL has no gamepad mapping for
+/// get a `Joystick` component the same way. Their state is polled at `OnLoad` and merged into
+/// `InputState` so `InputMap` actions see every connected device.
+/// Requires `SDL_INIT_GAMEPAD` and `InputModule`.
+#[derive(Component)]
+pub struct GamepadModule;
+
+/// Defaults for newly connected gamepads.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GamepadSettings {
+    /// Stick and trigger values below this are treated as zero, the rest is rescaled to 0..1.
+    pub deadzone: f32,
+}
+
+#[derive(Component)]
+pub struct Gamepad {
+    pub handle: *mut SDL_Gamepad,
+    pub id: SDL_JoystickID,
+    pub name: String,
+    pub deadzone: f32,
+    /// Bit `n` is set while `SDL_GamepadButton(n)` is held.
+    pub buttons: u64,
+    pub previous_buttons: u64,
+    /// Indexed by `SDL_GamepadAxis` with the deadzone applied. Sticks are -1..1, triggers 0..1.
+    pub axes: [f32; 6],
+}
+
+/// A joystick without a gamepad mapping, e.g. a flight or arcade stick. Buttons and axes are
+/// reported by index since their layout depends on the device.
+#[derive(Component)]
+pub struct Joystick {
+    pub handle: *mut SDL_Joystick,
+    pub id: SDL_JoystickID,
+    pub name: String,
+    pub deadzone: f32,
+    /// Bit `n` is set while button `n` is held, buttons past the 64th are ignored.
+    pub buttons: u64,
+    pub previous_buttons: u64,
+    /// The first `JOYSTICK_AXES` axes with the deadzone applied, -1..1.
+    pub axes: [f32; JOYSTICK_AXES],
+}
+
+/// Add to a gamepad or joystick entity to start a rumble, it is removed once sent. A new request replaces
+/// the one still playing.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Rumble {
+    /// Motor intensities in 0..1.
+    pub low_frequency: f32,
+    pub high_frequency: f32,
+    pub duration_ms: u32,
+}
+
+unsafe impl Send for Gamepad {}
+unsafe impl Sync for Gamepad {}
+
+unsafe impl Send for Joystick {}
+unsafe impl Sync for Joystick {}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self { deadzone: 0.15 }
+    }
+}
+
+impl Gamepad {
+    fn mask(button: SDL_GamepadButton) -> u64 {
+        if button.0 < 0 { 0 } else { 1 << button.0 }
+    }
+
+    pub fn pressed(&self, button: SDL_GamepadButton) -> bool {
+        (self.buttons & Self::mask(button)) != 0
+    }
+
+    pub fn just_pressed(&self, button: SDL_GamepadButton) -> bool {
+        self.pressed(button) && (self.previous_buttons & Self::mask(button)) == 0
+    }
+
+    pub fn just_released(&self, button: SDL_GamepadButton) -> bool {
+        !self.pressed(button) && (self.previous_buttons & Self::mask(button)) != 0
+    }
+
+    pub fn axis(&self, axis: SDL_GamepadAxis) -> f32 {
+        self.axes.get(axis.0 as usize).copied().unwrap_or(0.0)
+    }
+
+    /// `y` points down, like SDL reports it.
+    pub fn left_stick(&self) -> Vec2 {
+        Vec2::new(self.axis(SDL_GAMEPAD_AXIS_LEFTX), self.axis(SDL_GAMEPAD_AXIS_LEFTY))
+    }
+
+    pub fn right_stick(&self) -> Vec2 {
+        Vec2::new(self.axis(SDL_GAMEPAD_AXIS_RIGHTX), self.axis(SDL_GAMEPAD_AXIS_RIGHTY))
+    }
+
+    fn poll(&mut self) {
+        unsafe {
+            self.previous_buttons = self.buttons;
+            self.buttons = 0;
+            for button in 0..SDL_GAMEPAD_BUTTON_COUNT.0 {
+                if SDL_GetGamepadButton(self.handle, SDL_GamepadButton(button)) {
+                    self.buttons |= 1 << button;
+                }
+            }
+
+            let read = |axis: SDL_GamepadAxis| {
+                ((SDL_GetGamepadAxis(self.handle, axis) as f32) / 32767.0).clamp(-1.0, 1.0)
+            };
+
+            // Sticks use a radial deadzone so diagonals are not snapped to the axes
+            let left = apply_deadzone(
+                Vec2::new(read(SDL_GAMEPAD_AXIS_LEFTX), read(SDL_GAMEPAD_AXIS_LEFTY)),
+                self.deadzone
+            );
+            let right = apply_deadzone(
+                Vec2::new(read(SDL_GAMEPAD_AXIS_RIGHTX), read(SDL_GAMEPAD_AXIS_RIGHTY)),
+                self.deadzone
+            );
+            let left_trigger = apply_deadzone(
+                Vec2::new(read(SDL_GAMEPAD_AXIS_LEFT_TRIGGER), 0.0),
+                self.deadzone
+            );
+            let right_trigger = apply_deadzone(
+                Vec2::new(read(SDL_GAMEPAD_AXIS_RIGHT_TRIGGER), 0.0),
+                self.deadzone
+            );
+
+            self.axes[SDL_GAMEPAD_AXIS_LEFTX.0 as usize] = left.x;
+            self.axes[SDL_GAMEPAD_AXIS_LEFTY.0 as usize] = left.y;
+            self.axes[SDL_GAMEPAD_AXIS_RIGHTX.0 as usize] = right.x;
+            self.axes[SDL_GAMEPAD_AXIS_RIGHTY.0 as usize] = right.y;
+            self.axes[SDL_GAMEPAD_AXIS_LEFT_TRIGGER.0 as usize] = left_trigger.x;
+            self.axes[SDL_GAMEPAD_AXIS_RIGHT_TRIGGER.0 as usize] = right_trigger.x;
+        }
+    }
+}
+
+impl Joystick {
+    fn mask(button: u8) -> u64 {
+        if button < 64 { 1 << button } else { 0 }
+    }
+
+    pub fn pressed(&self, button: u8) -> bool {
+        (self.buttons & Self::mask(button)) != 0
+    }
+
+    pub fn just_pressed(&self, button: u8) -> bool {
+        self.pressed(button) && (self.previous_buttons & Self::mask(button)) == 0
+    }
+
+    pub fn just_released(&self, button: u8) -> bool {
+        !self.pressed(button) && (self.previous_buttons & Self::mask(button)) != 0
+    }
+
+    pub fn axis(&self, axis: usize) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+
+    fn poll(&mut self) {
+        unsafe {
+            self.previous_buttons = self.buttons;
+            self.buttons = 0;
+            for button in 0..SDL_GetNumJoystickButtons(self.handle).clamp(0, 64) {
+                if SDL_GetJoystickButton(self.handle, button) {
+                    self.buttons |= 1 << button;
+                }
+            }
+
+            for axis in 0..SDL_GetNumJoystickAxes(self.handle).clamp(0, JOYSTICK_AXES as c_int) {
+                let value = ((SDL_GetJoystickAxis(self.handle, axis) as f32) / 32767.0).clamp(-1.0, 1.0);
+                self.axes[axis as usize] = apply_deadzone(Vec2::new(value, 0.0), self.deadzone).x;
+            }
+        }
+    }
+}
+
+fn apply_deadzone(value: Vec2, deadzone: f32) -> Vec2 {
+    let length = value.length();
+    if length <= deadzone || deadzone >= 1.0 {
+        return Vec2::ZERO;
+    }
+
+    let scaled = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+    value * (scaled / length)
+}
+
+fn device_name(name_ptr: *const c_char) -> String {
+    if name_ptr.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(name_ptr).to_string_lossy().into_owned() }
+    }
+}
+
+fn to_motor(intensity: f32) -> u16 {
+    (intensity.clamp(0.0, 1.0) * 65535.0) as u16
+}
+
+impl Module for GamepadModule {
+    fn module(world: &World) {
+        world.component::<GamepadSettings>();
+        world.component::<Gamepad>();
+        world.component::<Joystick>();
+        world.component::<Rumble>();
+
+        world.set(GamepadSettings::default());
+
+        // Each closure below owns its queries
+        let removed_gamepads_query = world.query::<&Gamepad>().set_cached().build();
+        let removed_joysticks_query = world.query::<&Joystick>().set_cached().build();
+        let gamepads_query = world.query::<&Gamepad>().set_cached().build();
+        let joysticks_query = world.query::<&Joystick>().set_cached().build();
+
+        observer!("gamepad_device", world, GamepadDeviceEvent, flecs::Any).each_iter(
+            move |it, _, _| {
+                let event = &*it.param();
+                let world = it.world();
+
+                match event.kind {
+                    GamepadDeviceEventKind::Added => unsafe {
+                        let handle = SDL_OpenGamepad(event.which);
+                        if handle == null_mut() {
+                            println!(
+                                "Failed to open gamepad {}: {}",
+                                event.which,
+                                CStr::from_ptr(SDL_GetError()).to_string_lossy()
+                            );
+                            return;
+                        }
+
+                        let name = device_name(SDL_GetGamepadName(handle));
+
+                        let mut deadzone = 0.0;
+                        world.get::<&GamepadSettings>(|settings| {
+                            deadzone = settings.deadzone;
+                        });
+
+                        println!("Gamepad connected: {}", name);
+                        world.entity().set(Gamepad {
+                            handle,
+                            id: event.which,
+                            name,
+                            deadzone,
+                            buttons: 0,
+                            previous_buttons: 0,
+                            axes: [0.0; 6],
+                        });
+                    }
+                    GamepadDeviceEventKind::Removed => {
+                        // Destructed after the iteration, the query's tables must not change under it
+                        let mut removed = Vec::new();
+                        removed_gamepads_query.each_entity(|entity, gamepad| {
+                            if gamepad.id != event.which {
+                                return;
+                            }
+
+                            unsafe {
+                                SDL_CloseGamepad(gamepad.handle);
+                            }
+                            println!("Gamepad disconnected: {}", gamepad.name);
+                            removed.push(entity.id());
+                        });
+
+                        for entity in removed {
+                            world.entity_from_id(entity).destruct();
+                        }
+                    }
+                    GamepadDeviceEventKind::Remapped => {}
+                }
+            }
+        );
+
+        observer!("joystick_device", world, JoystickDeviceEvent, flecs::Any).each_iter(
+            move |it, _, _| {
+                let event = &*it.param();
+                let world = it.world();
+
+                match event.kind {
+                    JoystickDeviceEventKind::Added => unsafe {
+                        // Gamepads are joysticks too, "gamepad_device" opens those
+                        if SDL_IsGamepad(event.which) {
+                            return;
+                        }
+
+                        let handle = SDL_OpenJoystick(event.which);
+                        if handle == null_mut() {
+                            println!(
+                                "Failed to open joystick {}: {}",
+                                event.which,
+                                CStr::from_ptr(SDL_GetError()).to_string_lossy()
+                            );
+                            return;
+                        }
+
+                        let name = device_name(SDL_GetJoystickName(handle));
+
+                        let mut deadzone = 0.0;
+                        world.get::<&GamepadSettings>(|settings| {
+                            deadzone = settings.deadzone;
+                        });
+
+                        println!("Joystick connected: {}", name);
+                        world.entity().set(Joystick {
+                            handle,
+                            id: event.which,
+                            name,
+                            deadzone,
+                            buttons: 0,
+                            previous_buttons: 0,
+                            axes: [0.0; JOYSTICK_AXES],
+                        });
+                    }
+                    JoystickDeviceEventKind::Removed => {
+                        let mut removed = Vec::new();
+                        removed_joysticks_query.each_entity(|entity, joystick| {
+                            if joystick.id != event.which {
+                                return;
+                            }
+
+                            unsafe {
+                                SDL_CloseJoystick(joystick.handle);
+                            }
+                            println!("Joystick disconnected: {}", joystick.name);
+                            removed.push(entity.id());
+                        });
+
+                        for entity in removed {
+                            world.entity_from_id(entity).destruct();
+                        }
+                    }
+                }
+            }
+        );
+
+        system!("poll_gamepads", world, &mut Gamepad)
+            .kind::<OnLoad>()
+            .each(|gamepad| {
+                gamepad.poll();
+            });
+
+        system!("poll_joysticks", world, &mut Joystick)
+            .kind::<OnLoad>()
+            .each(|joystick| {
+                joystick.poll();
+            });
+
+        system!("gamepads_to_input", world, &mut InputState($))
+            .kind::<OnLoad>()
+            .each(move |state| {
+                state.gamepad_buttons = 0;
+                state.gamepad_axes = [0.0; 6];
+                state.joystick_buttons = 0;
+                state.joystick_axes = [0.0; JOYSTICK_AXES];
+
+                // Any device can drive the actions, the strongest axis value wins
+                gamepads_query.each(|gamepad| {
+                    state.gamepad_buttons |= gamepad.buttons;
+                    for (merged, value) in state.gamepad_axes.iter_mut().zip(gamepad.axes) {
+                        if value.abs() > merged.abs() {
+                            *merged = value;
+                        }
+                    }
+                });
+                joysticks_query.each(|joystick| {
+                    state.joystick_buttons |= joystick.buttons;
+                    for (merged, value) in state.joystick_axes.iter_mut().zip(joystick.axes) {
+                        if value.abs() > merged.abs() {
+                            *merged = value;
+                        }
+                    }
+                });
+            });
+
+        system!("gamepad_rumble", world, &Gamepad, &Rumble)
+            .kind::<OnLoad>()
+            .each_entity(|entity, (gamepad, rumble)| {
+                unsafe {
+                    if
+                        !SDL_RumbleGamepad(
+                            gamepad.handle,
+                            to_motor(rumble.low_frequency),
+                            to_motor(rumble.high_frequency),
+                            rumble.duration_ms
+                        )
+                    {
+                        println!(
+                            "Failed to rumble {}: {}",
+                            gamepad.name,
+                            CStr::from_ptr(SDL_GetError()).to_string_lossy()
+                        );
+                    }
+                }
+
+                entity.remove::<Rumble>();
+            });
+
+        system!("joystick_rumble", world, &Joystick, &Rumble)
+            .kind::<OnLoad>()
+            .each_entity(|entity, (joystick, rumble)| {
+                unsafe {
+                    if
+                        !SDL_RumbleJoystick(
+                            joystick.handle,
+                            to_motor(rumble.low_frequency),
+                            to_motor(rumble.high_frequency),
+                            rumble.duration_ms
+                        )
+                    {
+                        println!(
+                            "Failed to rumble {}: {}",
+                            joystick.name,
+                            CStr::from_ptr(SDL_GetError()).to_string_lossy()
+                        );
+                    }
+                }
+
+                entity.remove::<Rumble>();
+            });
+    }
+}
@@ -0,0 +1,232 @@
+use std::ptr::null_mut;
+
+use flecs_ecs::{ core::{ Entity, World }, macros::Component };
+use glam::{ Quat, Vec2, Vec3, Vec4 };
+use sdl3_sys::gpu::{ SDL_GPUDevice, SDL_GPUTexture };
+
+use crate::{
+    camera::RenderLayers,
+    gpu::upload_rgba,
+    modules::mesh::{ GlobalTransform3D, Material, Mesh, Transform3D, Vertex3D },
+    BASE_PATH,
+};
+
+/// Marks an entity spawned from a glTF node, `name` is empty for unnamed nodes.
+#[derive(Component, Clone, Debug)]
+pub struct GltfNode {
+    pub name: String,
+}
+
+/// Root entity of a loaded model.
+#[derive(Component, Clone, Debug)]
+pub struct GltfModel {
+    /// Parts of the model that could not be loaded but did not stop the import.
+    pub warnings: Vec<String>,
+}
+
+/// Loads `Models/<file_name>` (`.gltf` or `.glb`) and spawns its default scene under a new root
+/// entity, which is returned. Every node becomes a child entity with `Transform3D` and
+/// `GlobalTransform3D`, and each primitive of a node's mesh a child of that node with its
+/// `Mesh` and `Material`. The root also gets a `GltfModel` listing what was skipped: primitives
+/// that are not triangle lists or have mismatched attributes or out of range indices, and
+/// images in formats other than 8-bit.
+///
+/// glTF is right-handed, positions and rotations are mirrored on z into the left-handed space the
+/// cameras use.
+pub fn load_gltf(
+    world: &World,
+    gpu_device: *mut SDL_GPUDevice,
+    file_name: &str,
+    render_layers: RenderLayers
+) -> Result<Entity, String> {
+    let path = format!("{}/Models/{}", BASE_PATH, file_name);
+    let (document, buffers, images) = gltf::import(&path)
+        .map_err(|error| format!("{}: {}", path, error))?;
+
+    let mut warnings = Vec::new();
+
+    // Images are uploaded the first time a material uses them, null draws white
+    let mut textures: Vec<Option<*mut SDL_GPUTexture>> = vec![None; images.len()];
+    let mut materials: Vec<Material> = Vec::with_capacity(document.materials().len());
+    for material in document.materials() {
+        let pbr = material.pbr_metallic_roughness();
+        let texture = pbr.base_color_texture().map_or(null_mut(), |info| {
+            let index = info.texture().source().index();
+            *textures[index].get_or_insert_with(|| {
+                upload_image(gpu_device, &images[index]).unwrap_or_else(|error| {
+                    warnings.push(format!("image {}: {}", index, error));
+                    null_mut()
+                })
+            })
+        });
+
+        materials.push(Material {
+            base_color: Vec4::from(pbr.base_color_factor()),
+            texture,
+        });
+    }
+
+    let mut meshes: Vec<Vec<(Mesh, Material)>> = Vec::with_capacity(document.meshes().len());
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            let name = format!("mesh {} primitive {}", mesh.index(), primitive.index());
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                warnings.push(format!("{}: skipped {:?} primitive", name, primitive.mode()));
+                continue;
+            }
+
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions: Vec<Vec3> = reader
+                .read_positions()
+                .ok_or_else(|| format!("{}: primitive without positions", path))?
+                .map(|position| Vec3::new(position[0], position[1], -position[2]))
+                .collect();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            // The GPU would read past the vertex buffer
+            if indices.iter().any(|index| (*index as usize) >= positions.len()) {
+                warnings.push(format!("{}: skipped, an index is out of range", name));
+                continue;
+            }
+
+            let normals: Vec<Vec3> = match reader.read_normals() {
+                Some(normals) => normals.map(|normal| Vec3::new(normal[0], normal[1], -normal[2])).collect(),
+                None => smooth_normals(&positions, &indices),
+            };
+            let uvs: Vec<Vec2> = match reader.read_tex_coords(0) {
+                Some(uvs) => uvs.into_f32().map(Vec2::from).collect(),
+                None => vec![Vec2::ZERO; positions.len()],
+            };
+            if normals.len() != positions.len() || uvs.len() != positions.len() {
+                warnings.push(format!("{}: skipped, its attributes have different lengths", name));
+                continue;
+            }
+
+            let vertices: Vec<Vertex3D> = positions
+                .iter()
+                .zip(normals.iter())
+                .zip(uvs.iter())
+                .map(|((position, normal), uv)| Vertex3D {
+                    position: *position,
+                    normal: *normal,
+                    uv: *uv,
+                })
+                .collect();
+
+            let material = primitive
+                .material()
+                .index()
+                .map_or_else(Material::default, |index| materials[index]);
+            primitives.push((Mesh::new(gpu_device, &vertices, &indices), material));
+        }
+        meshes.push(primitives);
+    }
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| format!("{}: no scene", path))?;
+
+    let root = world
+        .entity()
+        .set(GltfNode { name: file_name.to_owned() })
+        .set(GltfModel { warnings })
+        .set(Transform3D::default())
+        .set(GlobalTransform3D::default())
+        .id();
+    for node in scene.nodes() {
+        spawn_node(world, &node, root, &meshes, render_layers);
+    }
+
+    Ok(root)
+}
+
+fn spawn_node(
+    world: &World,
+    node: &gltf::Node,
+    parent: Entity,
+    meshes: &[Vec<(Mesh, Material)>],
+    render_layers: RenderLayers
+) {
+    let (translation, rotation, scale) = node.transform().decomposed();
+    let entity = world
+        .entity()
+        .child_of_id(parent)
+        .set(GltfNode { name: node.name().unwrap_or_default().to_owned() })
+        .set(Transform3D {
+            translation: Vec3::new(translation[0], translation[1], -translation[2]),
+            rotation: Quat::from_xyzw(-rotation[0], -rotation[1], rotation[2], rotation[3]),
+            scale: Vec3::from(scale),
+        })
+        .set(GlobalTransform3D::default())
+        .id();
+
+    if let Some(mesh) = node.mesh() {
+        for (mesh, material) in meshes[mesh.index()].iter() {
+            world
+                .entity()
+                .child_of_id(entity)
+                .set(*mesh)
+                .set(*material)
+                .set(Transform3D::default())
+                .set(GlobalTransform3D::default())
+                .set(render_layers);
+        }
+    }
+
+    for child in node.children() {
+        spawn_node(world, &child, entity, meshes, render_layers);
+    }
+}
+
+/// Area weighted vertex normals for primitives that come without them, `indices` must be in
+/// range of `positions`.
+fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        normals[a] += normal;
+        normals[b] += normal;
+        normals[c] += normal;
+    }
+
+    normals.iter().map(|normal| normal.normalize_or(Vec3::Y)).collect()
+}
+
+/// Converts an 8-bit glTF image to RGBA and uploads it like `SpritesBuffer::new` does with its
+/// BMPs. Other formats are not supported.
+fn upload_image(gpu_device: *mut SDL_GPUDevice, image: &gltf::image::Data) -> Result<*mut SDL_GPUTexture, String> {
+    use gltf::image::Format;
+
+    let channels = match image.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        format => {
+            return Err(format!("unsupported format {:?}", format));
+        }
+    };
+
+    let mut rgba = Vec::with_capacity(image.pixels.len() / channels * 4);
+    for pixel in image.pixels.chunks_exact(channels) {
+        rgba.extend_from_slice(
+            &(match pixel {
+                [l] => [*l, *l, *l, 255],
+                [l, a] => [*l, *l, *l, *a],
+                [r, g, b] => [*r, *g, *b, 255],
+                _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            })
+        );
+    }
+
+    let texture = upload_rgba(gpu_device, image.width, image.height, &rgba);
+    if texture.is_null() {
+        return Err("failed to upload".to_owned());
+    }
+    Ok(texture)
+}
@@ -0,0 +1,538 @@
+use std::{ collections::HashMap, ffi::{ CStr, CString }, os::raw::c_int };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::{ OnLoad, PostFrame, PostLoad } }, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, SystemAPI },
+};
+use glam::Vec2;
+use sdl3_sys::{ gamepad::*, keyboard::SDL_GetKeyboardState, scancode::* };
+
+use crate::{
+    events::{
+        MouseButtonEvent,
+        MouseMotionEvent,
+        MouseWheelEvent,
+    },
+    BASE_PATH,
+};
+
+/// Maps named actions to keys, mouse buttons, gamepad and joystick inputs. Raw device state is
+/// sampled into `InputState` at `OnLoad` and `InputMap` actions are evaluated from it at
+/// `PostLoad`, so systems in later phases can query them. `Mouse` is updated the same way.
+#[derive(Component)]
+pub struct InputModule;
+
+/// Raw device state for the current frame.
+#[derive(Component, Clone, Default)]
+pub struct InputState {
+    /// Indexed by `SDL_Scancode`.
+    pub keys: Vec<bool>,
+    /// Bit `n - 1` is set while mouse button `n` is held.
+    pub mouse_buttons: u32,
+    /// Cursor position in window coordinates.
+    pub mouse_position: Vec2,
+    /// Motion and wheel accumulated from events during the frame, cleared at `PostFrame`.
+    pub mouse_delta: Vec2,
+    pub mouse_wheel: Vec2,
+    /// Bit `n` is set while `SDL_GamepadButton(n)` is held on any gamepad, see `GamepadModule`.
+    pub gamepad_buttons: u64,
+    /// Indexed by `SDL_GamepadAxis` with the deadzone applied.
+    pub gamepad_axes: [f32; 6],
+    /// Bit `n` is set while button `n` is held on any joystick that is not a gamepad.
+    pub joystick_buttons: u64,
+    /// The first `JOYSTICK_AXES` axes of joysticks that are not gamepads, with the deadzone
+    /// applied.
+    pub joystick_axes: [f32; JOYSTICK_AXES],
+}
+
+/// Mouse state for the current frame.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Mouse {
+    /// Cursor position in window coordinates, multiply by the pixel density for viewport pixels.
+    pub position: Vec2,
+    /// Cursor movement since the previous frame.
+    pub delta: Vec2,
+    pub wheel: Vec2,
+    /// Bit `n - 1` is set while mouse button `n` is held.
+    pub buttons: u32,
+    pub previous_buttons: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Key(SDL_Scancode),
+    /// `SDL_BUTTON_LEFT`, `SDL_BUTTON_MIDDLE`, ...
+    MouseButton(u8),
+    GamepadButton(SDL_GamepadButton),
+    /// One half of a gamepad axis, its value is 0..1 in that direction.
+    GamepadAxis {
+        axis: SDL_GamepadAxis,
+        positive: bool,
+    },
+    /// Button index of a joystick that is not a gamepad, below 64.
+    JoystickButton(u8),
+    /// One half of a joystick axis by index, below `JOYSTICK_AXES`.
+    JoystickAxis {
+        axis: u8,
+        positive: bool,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub enum Action {
+    Button(Vec<Input>),
+    Axis1D {
+        negative: Vec<Input>,
+        positive: Vec<Input>,
+    },
+    Axis2D {
+        left: Vec<Input>,
+        right: Vec<Input>,
+        down: Vec<Input>,
+        up: Vec<Input>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ActionState {
+    pub value: Vec2,
+    pub pressed: bool,
+    pub was_pressed: bool,
+}
+
+#[derive(Component, Default)]
+pub struct InputMap {
+    pub actions: HashMap<String, Action>,
+    pub states: HashMap<String, ActionState>,
+}
+
+/// Joystick axes sampled into `InputState`, the rest are ignored.
+pub const JOYSTICK_AXES: usize = 8;
+
+/// Values above this count as pressed, for both digital and analog inputs.
+const PRESS_THRESHOLD: f32 = 0.5;
+
+const MOUSE_BUTTON_NAMES: [&str; 5] = ["left", "middle", "right", "x1", "x2"];
+
+/// Splits the `+` or `-` off an axis name.
+fn axis_direction(name: &str) -> Result<(bool, &str), String> {
+    if let Some(axis_name) = name.strip_prefix('+') {
+        Ok((true, axis_name))
+    } else if let Some(axis_name) = name.strip_prefix('-') {
+        Ok((false, axis_name))
+    } else {
+        Err(format!("Axis '{}' must start with + or -", name))
+    }
+}
+
+/// Bit of mouse button `button` in `mouse_buttons`, 0 for buttons that do not fit in it.
+fn mouse_mask(button: u8) -> u32 {
+    match button {
+        1..=32 => 1 << (button - 1),
+        _ => 0,
+    }
+}
+
+impl InputState {
+    pub fn input_value(&self, input: &Input) -> f32 {
+        let digital = |held: bool| if held { 1.0 } else { 0.0 };
+
+        match *input {
+            Input::Key(scancode) =>
+                digital(self.keys.get(scancode.0 as usize).copied().unwrap_or(false)),
+            Input::MouseButton(button) =>
+                digital((self.mouse_buttons & mouse_mask(button)) != 0),
+            Input::GamepadButton(button) =>
+                digital(button.0 >= 0 && (self.gamepad_buttons & (1 << button.0)) != 0),
+            Input::GamepadAxis { axis, positive } => {
+                let value = self.gamepad_axes.get(axis.0 as usize).copied().unwrap_or(0.0);
+                if positive { value.max(0.0) } else { (-value).max(0.0) }
+            }
+            Input::JoystickButton(button) =>
+                digital(button < 64 && (self.joystick_buttons & (1 << button)) != 0),
+            Input::JoystickAxis { axis, positive } => {
+                let value = self.joystick_axes.get(axis as usize).copied().unwrap_or(0.0);
+                if positive { value.max(0.0) } else { (-value).max(0.0) }
+            }
+        }
+    }
+
+    fn max_value(&self, inputs: &[Input]) -> f32 {
+        inputs
+            .iter()
+            .map(|input| self.input_value(input))
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Mouse {
+    /// `button` is `SDL_BUTTON_LEFT`, `SDL_BUTTON_MIDDLE`, ...
+    pub fn pressed(&self, button: u8) -> bool {
+        (self.buttons & mouse_mask(button)) != 0
+    }
+
+    pub fn just_pressed(&self, button: u8) -> bool {
+        self.pressed(button) && (self.previous_buttons & mouse_mask(button)) == 0
+    }
+
+    pub fn just_released(&self, button: u8) -> bool {
+        !self.pressed(button) && (self.previous_buttons & mouse_mask(button)) != 0
+    }
+}
+
+impl Input {
+    /// Parses `key:<scancode name>`, `mouse:<left|middle|right|x1|x2|button number>`,
+    /// `gamepad:<button name>` or `axis:<+|-><axis name>` using SDL's names, or
+    /// `joystick:<button index>` and `joystick_axis:<+|-><axis index>`. Mouse buttons are
+    /// numbered from 1 up to 32.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (device, name) = text
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid input '{}'", text))?;
+        let c_name = CString::new(name).map_err(|_| format!("Invalid input '{}'", text))?;
+
+        unsafe {
+            match device {
+                "key" => {
+                    let scancode = SDL_GetScancodeFromName(c_name.as_ptr());
+                    if scancode == SDL_SCANCODE_UNKNOWN {
+                        return Err(format!("Unknown key '{}'", name));
+                    }
+                    Ok(Input::Key(scancode))
+                }
+                "mouse" => {
+                    let button = match MOUSE_BUTTON_NAMES.iter().position(|button| *button == name) {
+                        Some(index) => (index + 1) as u8,
+                        None => name.parse::<u8>().unwrap_or(0),
+                    };
+                    if mouse_mask(button) == 0 {
+                        return Err(format!("Unknown mouse button '{}'", name));
+                    }
+                    Ok(Input::MouseButton(button))
+                }
+                "gamepad" => {
+                    let button = SDL_GetGamepadButtonFromString(c_name.as_ptr());
+                    if button == SDL_GAMEPAD_BUTTON_INVALID {
+                        return Err(format!("Unknown gamepad button '{}'", name));
+                    }
+                    Ok(Input::GamepadButton(button))
+                }
+                "axis" => {
+                    let (positive, axis_name) = axis_direction(name)?;
+                    let c_axis_name = CString::new(axis_name).map_err(|_| format!("Invalid input '{}'", text))?;
+                    let axis = SDL_GetGamepadAxisFromString(c_axis_name.as_ptr());
+                    if axis == SDL_GAMEPAD_AXIS_INVALID {
+                        return Err(format!("Unknown gamepad axis '{}'", axis_name));
+                    }
+                    Ok(Input::GamepadAxis { axis, positive })
+                }
+                "joystick" => {
+                    match name.parse::<u8>() {
+                        Ok(button) if button < 64 => Ok(Input::JoystickButton(button)),
+                        _ => Err(format!("Unknown joystick button '{}'", name)),
+                    }
+                }
+                "joystick_axis" => {
+                    let (positive, axis_name) = axis_direction(name)?;
+                    match axis_name.parse::<u8>() {
+                        Ok(axis) if (axis as usize) < JOYSTICK_AXES =>
+                            Ok(Input::JoystickAxis { axis, positive }),
+                        _ => Err(format!("Unknown joystick axis '{}'", axis_name)),
+                    }
+                }
+                _ => Err(format!("Unknown input device '{}'", device)),
+            }
+        }
+    }
+
+    pub fn name(&self) -> String {
+        unsafe {
+            match *self {
+                Input::Key(scancode) =>
+                    format!("key:{}", CStr::from_ptr(SDL_GetScancodeName(scancode)).to_string_lossy()),
+                Input::MouseButton(button) =>
+                    match MOUSE_BUTTON_NAMES.get((button as usize).wrapping_sub(1)) {
+                        Some(name) => format!("mouse:{}", name),
+                        None => format!("mouse:{}", button),
+                    }
+                Input::GamepadButton(button) =>
+                    format!(
+                        "gamepad:{}",
+                        CStr::from_ptr(SDL_GetGamepadStringForButton(button)).to_string_lossy()
+                    ),
+                Input::GamepadAxis { axis, positive } =>
+                    format!(
+                        "axis:{}{}",
+                        if positive { "+" } else { "-" },
+                        CStr::from_ptr(SDL_GetGamepadStringForAxis(axis)).to_string_lossy()
+                    ),
+                Input::JoystickButton(button) => format!("joystick:{}", button),
+                Input::JoystickAxis { axis, positive } =>
+                    format!("joystick_axis:{}{}", if positive { "+" } else { "-" }, axis),
+            }
+        }
+    }
+}
+
+impl Action {
+    fn slots(&self) -> Vec<(&'static str, &Vec<Input>)> {
+        match self {
+            Action::Button(inputs) => vec![("button", inputs)],
+            Action::Axis1D { negative, positive } => vec![("negative", negative), ("positive", positive)],
+            Action::Axis2D { left, right, down, up } =>
+                vec![("left", left), ("right", right), ("down", down), ("up", up)],
+        }
+    }
+
+    fn slot_mut(&mut self, slot: &str) -> Option<&mut Vec<Input>> {
+        match (self, slot) {
+            (Action::Button(inputs), "button") => Some(inputs),
+            (Action::Axis1D { negative, .. }, "negative") => Some(negative),
+            (Action::Axis1D { positive, .. }, "positive") => Some(positive),
+            (Action::Axis2D { left, .. }, "left") => Some(left),
+            (Action::Axis2D { right, .. }, "right") => Some(right),
+            (Action::Axis2D { down, .. }, "down") => Some(down),
+            (Action::Axis2D { up, .. }, "up") => Some(up),
+            _ => None,
+        }
+    }
+
+    fn empty_for_slot(slot: &str) -> Option<Self> {
+        match slot {
+            "button" => Some(Action::Button(Vec::new())),
+            "negative" | "positive" =>
+                Some(Action::Axis1D {
+                    negative: Vec::new(),
+                    positive: Vec::new(),
+                }),
+            "left" | "right" | "down" | "up" =>
+                Some(Action::Axis2D {
+                    left: Vec::new(),
+                    right: Vec::new(),
+                    down: Vec::new(),
+                    up: Vec::new(),
+                }),
+            _ => None,
+        }
+    }
+
+    pub fn evaluate(&self, state: &InputState) -> Vec2 {
+        match self {
+            Action::Button(inputs) => Vec2::new(state.max_value(inputs), 0.0),
+            Action::Axis1D { negative, positive } =>
+                Vec2::new(state.max_value(positive) - state.max_value(negative), 0.0),
+            Action::Axis2D { left, right, down, up } => {
+                let value = Vec2::new(
+                    state.max_value(right) - state.max_value(left),
+                    state.max_value(up) - state.max_value(down)
+                );
+                // Keyboard diagonals would otherwise be faster than straight movement
+                value.clamp_length_max(1.0)
+            }
+        }
+    }
+}
+
+impl InputMap {
+    pub fn bind(&mut self, name: &str, action: Action) {
+        self.actions.insert(name.to_owned(), action);
+    }
+
+    /// Adds `input` to the `slot` of the action, creating the action from the slot kind when
+    /// it does not exist yet.
+    pub fn bind_input(&mut self, name: &str, slot: &str, input: Input) -> Result<(), String> {
+        if !self.actions.contains_key(name) {
+            let action = Action::empty_for_slot(slot).ok_or_else(||
+                format!("Unknown slot '{}'", slot)
+            )?;
+            self.actions.insert(name.to_owned(), action);
+        }
+
+        let action = self.actions.get_mut(name).unwrap();
+        let inputs = action
+            .slot_mut(slot)
+            .ok_or_else(|| format!("Action '{}' has no '{}' slot", name, slot))?;
+        inputs.push(input);
+        Ok(())
+    }
+
+    /// Removes every input bound to the action slot, for rebinding.
+    pub fn clear_slot(&mut self, name: &str, slot: &str) {
+        if let Some(inputs) = self.actions.get_mut(name).and_then(|action| action.slot_mut(slot)) {
+            inputs.clear();
+        }
+    }
+
+    /// Parses bindings, one `<action> <slot> <input>` per line. Empty lines and lines starting
+    /// with `#` are ignored.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut input_map = InputMap::default();
+
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [name, slot, input] = parts[..] else {
+                return Err(format!("Line {}: expected '<action> <slot> <input>'", line_number + 1));
+            };
+
+            Input::parse(input)
+                .and_then(|input| input_map.bind_input(name, slot, input))
+                .map_err(|error| format!("Line {}: {}", line_number + 1, error))?;
+        }
+
+        Ok(input_map)
+    }
+
+    /// Loads bindings from `Config/<file_name>`.
+    pub fn load(file_name: &str) -> Result<Self, String> {
+        let path = format!("{}/Config/{}", BASE_PATH, file_name);
+        let text = std::fs::read_to_string(&path).map_err(|error| format!("{}: {}", path, error))?;
+        Self::parse(&text)
+    }
+
+    pub fn save(&self, file_name: &str) -> Result<(), String> {
+        let path = format!("{}/Config/{}", BASE_PATH, file_name);
+        std::fs::write(&path, self.to_config()).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn to_config(&self) -> String {
+        let mut names: Vec<&String> = self.actions.keys().collect();
+        names.sort();
+
+        let mut text = String::new();
+        for name in names {
+            for (slot, inputs) in self.actions[name].slots() {
+                for input in inputs {
+                    text.push_str(&format!("{} {} {}\n", name, slot, input.name()));
+                }
+            }
+        }
+        text
+    }
+
+    pub fn update(&mut self, state: &InputState) {
+        for (name, action) in self.actions.iter() {
+            let value = action.evaluate(state);
+            let action_state = self.states.entry(name.clone()).or_default();
+            action_state.was_pressed = action_state.pressed;
+            action_state.value = value;
+            action_state.pressed = value.length() > PRESS_THRESHOLD;
+        }
+    }
+
+    fn state(&self, name: &str) -> ActionState {
+        self.states.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn pressed(&self, name: &str) -> bool {
+        self.state(name).pressed
+    }
+
+    pub fn just_pressed(&self, name: &str) -> bool {
+        let state = self.state(name);
+        state.pressed && !state.was_pressed
+    }
+
+    pub fn just_released(&self, name: &str) -> bool {
+        let state = self.state(name);
+        !state.pressed && state.was_pressed
+    }
+
+    /// Value of a button or 1D axis action.
+    pub fn value(&self, name: &str) -> f32 {
+        self.state(name).value.x
+    }
+
+    /// Value of a 2D axis action, `y` points up.
+    pub fn axis2d(&self, name: &str) -> Vec2 {
+        self.state(name).value
+    }
+}
+
+impl Module for InputModule {
+    fn module(world: &World) {
+        world.component::<InputState>();
+        world.component::<InputMap>();
+        world.component::<Mouse>();
+
+        world.set(InputState::default());
+        world.set(InputMap::default());
+        world.set(Mouse::default());
+
+        observer!("input_mouse_button", world, MouseButtonEvent, flecs::Any).each_iter(
+            |it, _, _| {
+                let event = &*it.param();
+                let mask = mouse_mask(event.button);
+                if mask == 0 {
+                    return;
+                }
+
+                it.world().get::<&mut InputState>(|state| {
+                    if event.down {
+                        state.mouse_buttons |= mask;
+                    } else {
+                        state.mouse_buttons &= !mask;
+                    }
+                });
+            }
+        );
+
+        observer!("input_mouse_motion", world, MouseMotionEvent, flecs::Any).each_iter(
+            |it, _, _| {
+                let event = &*it.param();
+                it.world().get::<&mut InputState>(|state| {
+                    state.mouse_position = Vec2::new(event.x, event.y);
+                    state.mouse_delta += Vec2::new(event.xrel, event.yrel);
+                });
+            }
+        );
+
+        observer!("input_mouse_wheel", world, MouseWheelEvent, flecs::Any).each_iter(
+            |it, _, _| {
+                let event = &*it.param();
+                it.world().get::<&mut InputState>(|state| {
+                    state.mouse_wheel += Vec2::new(event.x, event.y);
+                });
+            }
+        );
+
+        system!("sample_keyboard", world, &mut InputState($))
+            .kind::<OnLoad>()
+            .each(|state| unsafe {
+                let mut numkeys: c_int = 0;
+                let key_state_ptr = SDL_GetKeyboardState(&mut numkeys);
+                let key_states = std::slice::from_raw_parts(key_state_ptr, numkeys as usize);
+
+                state.keys.clear();
+                state.keys.extend_from_slice(key_states);
+            });
+
+        system!("update_input_actions", world, &InputState($), &mut InputMap($))
+            .kind::<PostLoad>()
+            .each(|(state, input_map)| {
+                input_map.update(state);
+            });
+
+        system!("update_mouse", world, &InputState($), &mut Mouse($))
+            .kind::<PostLoad>()
+            .each(|(state, mouse)| {
+                mouse.previous_buttons = mouse.buttons;
+                mouse.buttons = state.mouse_buttons;
+                mouse.position = state.mouse_position;
+                mouse.delta = state.mouse_delta;
+                mouse.wheel = state.mouse_wheel;
+            });
+
+        system!("clear_mouse_motion", world, &mut InputState($))
+            .kind::<PostFrame>()
+            .each(|state| {
+                state.mouse_delta = Vec2::ZERO;
+                state.mouse_wheel = Vec2::ZERO;
+            });
+    }
+}
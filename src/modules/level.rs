@@ -0,0 +1,313 @@
+use std::{ collections::HashMap, path::{ Path, PathBuf } };
+
+use flecs_ecs::{
+    core::{ Entity, EntityView, World, WorldGet },
+    macros::Component,
+    prelude::Module,
+};
+use glam::{ Vec2, Vec3 };
+use sdl3_sys::gpu::{ SDL_GPUDevice, SDL_GPUTexture };
+
+use crate::{
+    gpu::upload_rgba,
+    modules::{
+        tilemap::{ TileLayer, Tileset, TILE_FLAGS },
+        transform::{ GlobalTransform2D, Transform2D },
+    },
+};
+
+pub mod ldtk;
+pub mod tiled;
+
+/// Levels authored in Tiled (`tiled::load_tiled`) or LDtk (`ldtk::load_ldtk`), loaded from
+/// `Levels/`. A level is spawned under a root entity with `Level`:
+///
+/// - tile layers become `TileLayer` entities, one per tileset the layer uses,
+/// - objects and LDtk entities become entities with `Transform2D`, `LevelObject` and a
+///   `Collider` from their shape, then the `LevelSpawners` registered for their class or for
+///   one of their property names add the game's components,
+/// - collision shapes of tileset tiles and LDtk IntGrid cells become `Collider` entities in
+///   level space.
+///
+/// Level units are pixels with y down, like the sprites. Layers are placed from `LAYER_Z_START`
+/// up in steps of `LAYER_Z_STEP`, so later layers cover earlier ones and sprites at z 0 are
+/// drawn on top. Needs `TilemapModule` and `TransformModule`.
+#[derive(Component)]
+pub struct LevelModule;
+
+pub const LAYER_Z_START: f32 = -0.9;
+pub const LAYER_Z_STEP: f32 = 0.01;
+
+/// Root entity of a loaded level.
+#[derive(Component, Clone, Debug)]
+pub struct Level {
+    pub name: String,
+    /// In level units.
+    pub size: Vec2,
+    /// Parts of the level that could not be loaded but did not stop the import.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// Also colors, files, enums and everything else without a better match.
+    String(String),
+}
+
+/// An object placed in the editor with its custom properties.
+#[derive(Component, Clone, Debug, Default)]
+pub struct LevelObject {
+    pub name: String,
+    /// Tiled class (or type), LDtk entity identifier.
+    pub class: String,
+    pub properties: HashMap<String, PropertyValue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Rect { position: Vec2, size: Vec2 },
+    Ellipse { center: Vec2, radii: Vec2 },
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+    Point(Vec2),
+}
+
+/// Collision shapes relative to the entity's `Transform2D`.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Collider {
+    pub shapes: Vec<Shape>,
+}
+
+/// Adds components to an entity spawned from a `LevelObject`.
+pub type LevelSpawner = fn(EntityView<'_>, &LevelObject);
+
+/// Spawners by class or property name. An object runs the spawner of its class first, then
+/// those of its property names in alphabetical order, so one object can combine several.
+#[derive(Component, Clone, Default)]
+pub struct LevelSpawners(pub HashMap<String, LevelSpawner>);
+
+/// A tile to place in a layer, `tile` as in `TileLayer::set`.
+pub(crate) struct PlacedTile {
+    pub x: u32,
+    pub y: u32,
+    pub tileset: usize,
+    pub tile: u32,
+}
+
+impl LevelObject {
+    pub fn get(&self, name: &str) -> Option<&PropertyValue> {
+        self.properties.get(name)
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            PropertyValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Integers, and floats truncated.
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            PropertyValue::Int(value) => Some(*value),
+            PropertyValue::Float(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    /// Floats, and integers converted.
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            PropertyValue::Float(value) => Some(*value),
+            PropertyValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            PropertyValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Shape {
+    pub fn translated(&self, offset: Vec2) -> Self {
+        match self {
+            Shape::Rect { position, size } => Shape::Rect { position: *position + offset, size: *size },
+            Shape::Ellipse { center, radii } => Shape::Ellipse { center: *center + offset, radii: *radii },
+            Shape::Polygon(points) => Shape::Polygon(points.iter().map(|point| *point + offset).collect()),
+            Shape::Polyline(points) => Shape::Polyline(points.iter().map(|point| *point + offset).collect()),
+            Shape::Point(point) => Shape::Point(*point + offset),
+        }
+    }
+}
+
+impl LevelSpawners {
+    pub fn register(&mut self, name: &str, spawner: LevelSpawner) {
+        self.0.insert(name.to_owned(), spawner);
+    }
+}
+
+/// Uploads a tileset image once per path, returns it with its size in pixels.
+pub(crate) fn load_tileset_texture(
+    gpu_device: *mut SDL_GPUDevice,
+    path: &Path,
+    cache: &mut HashMap<PathBuf, (*mut SDL_GPUTexture, Vec2)>
+) -> Result<(*mut SDL_GPUTexture, Vec2), String> {
+    if let Some(loaded) = cache.get(path) {
+        return Ok(*loaded);
+    }
+
+    let image = image::open(path)
+        .map_err(|error| format!("{}: {}", path.display(), error))?
+        .to_rgba8();
+    let texture = upload_rgba(gpu_device, image.width(), image.height(), image.as_raw());
+    if texture.is_null() {
+        return Err(format!("{}: failed to upload the tileset", path.display()));
+    }
+
+    let loaded = (texture, Vec2::new(image.width() as f32, image.height() as f32));
+    cache.insert(path.to_owned(), loaded);
+    Ok(loaded)
+}
+
+/// Spawns the root entity of a level.
+pub(crate) fn spawn_level(
+    world: &World,
+    name: &str,
+    size: Vec2,
+    origin: Vec2,
+    object: LevelObject,
+    warnings: Vec<String>
+) -> Entity {
+    let root = world
+        .entity()
+        .set(Level { name: name.to_owned(), size, warnings })
+        .set(Transform2D::from_translation(origin))
+        .set(GlobalTransform2D::default());
+    run_spawners(world, root, &object);
+    root.set(object).id()
+}
+
+/// Sorts a layer's tiles into `TileLayer`s of `size` cells, one per tileset. Cells holding more
+/// than one tile of the same tileset spill into extra layers just above.
+pub(crate) fn build_tile_layers(
+    size: (u32, u32),
+    cell_size: Vec2,
+    position: Vec3,
+    tilesets: &[Tileset],
+    tiles: &[PlacedTile]
+) -> Vec<TileLayer> {
+    let mut layers: Vec<(usize, TileLayer)> = Vec::new();
+    for placed in tiles {
+        if placed.tile & !TILE_FLAGS == 0 || placed.x >= size.0 || placed.y >= size.1 {
+            continue;
+        }
+
+        let free = layers
+            .iter()
+            .position(|(tileset, layer)| *tileset == placed.tileset && layer.get(placed.x, placed.y) == 0);
+        let index = match free {
+            Some(index) => index,
+            None => {
+                let stacked = layers.iter().filter(|(tileset, _)| *tileset == placed.tileset).count();
+                let z = position.z + ((stacked as f32) * LAYER_Z_STEP) / 8.0;
+                layers.push((
+                    placed.tileset,
+                    TileLayer::new(
+                        tilesets[placed.tileset],
+                        size.0,
+                        size.1,
+                        cell_size,
+                        Vec3::new(position.x, position.y, z)
+                    ),
+                ));
+                layers.len() - 1
+            }
+        };
+        layers[index].1.set(placed.x, placed.y, placed.tile);
+    }
+
+    layers
+        .into_iter()
+        .map(|(_, layer)| layer)
+        .collect()
+}
+
+/// Spawns a `TileLayer` under `parent`, named after the editor layer. `TileLayer::position` is in
+/// world space, it does not follow the parent's `Transform2D`.
+pub(crate) fn spawn_tile_layer(world: &World, parent: Entity, name: &str, layer: TileLayer) {
+    world
+        .entity()
+        .child_of_id(parent)
+        .set(LevelObject { name: name.to_owned(), ..Default::default() })
+        .set(layer);
+}
+
+/// Spawns an object under `parent` at `position` in level space and runs its spawners.
+pub(crate) fn spawn_object(
+    world: &World,
+    parent: Entity,
+    object: LevelObject,
+    position: Vec2,
+    rotation: f32,
+    shapes: Vec<Shape>
+) -> Entity {
+    let entity = world
+        .entity()
+        .child_of_id(parent)
+        .set(Transform2D {
+            translation: position,
+            rotation,
+            ..Default::default()
+        })
+        .set(GlobalTransform2D::default());
+    if !shapes.is_empty() {
+        entity.set(Collider { shapes });
+    }
+
+    run_spawners(world, entity, &object);
+    entity.set(object).id()
+}
+
+/// Spawns collision shapes given in level space, e.g. from tileset tiles, under `parent`.
+pub(crate) fn spawn_collider(world: &World, parent: Entity, object: LevelObject, shapes: Vec<Shape>) {
+    if !shapes.is_empty() {
+        spawn_object(world, parent, object, Vec2::ZERO, 0.0, shapes);
+    }
+}
+
+fn run_spawners(world: &World, entity: EntityView<'_>, object: &LevelObject) {
+    // Copied out so spawners are free to use the world
+    let mut spawners = HashMap::new();
+    world.get::<Option<&LevelSpawners>>(|level_spawners| {
+        if let Some(level_spawners) = level_spawners {
+            spawners = level_spawners.0.clone();
+        }
+    });
+
+    let mut names: Vec<&String> = object.properties.keys().collect();
+    names.sort();
+    let class = (!object.class.is_empty()).then_some(&object.class);
+    for name in class.into_iter().chain(names) {
+        if let Some(spawner) = spawners.get(name) {
+            spawner(entity, object);
+        }
+    }
+}
+
+impl Module for LevelModule {
+    fn module(world: &World) {
+        world.component::<Level>();
+        world.component::<LevelObject>();
+        world.component::<Collider>();
+        world.component::<LevelSpawners>();
+
+        world.set(LevelSpawners::default());
+    }
+}
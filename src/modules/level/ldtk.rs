@@ -0,0 +1,332 @@
+use std::{ collections::HashMap, path::{ Path, PathBuf } };
+
+use flecs_ecs::core::{ Entity, World };
+use glam::Vec2;
+use sdl3_sys::gpu::{ SDL_GPUDevice, SDL_GPUTexture };
+use serde_json::Value;
+
+use crate::{
+    modules::{
+        level::{
+            build_tile_layers,
+            load_tileset_texture,
+            spawn_collider,
+            spawn_level,
+            spawn_object,
+            spawn_tile_layer,
+            LevelObject,
+            PlacedTile,
+            PropertyValue,
+            Shape,
+            LAYER_Z_START,
+            LAYER_Z_STEP,
+        },
+        tilemap::{ Tileset, TILE_FLIP_HORIZONTAL, TILE_FLIP_VERTICAL },
+    },
+    BASE_PATH,
+};
+
+/// Largest layer accepted, in cells, so a corrupt `__cWid`/`__cHei` can't allocate gigabytes.
+const MAX_LAYER_CELLS: u32 = 1 << 24;
+
+/// Loads the level with identifier `level` from the LDtk project `Levels/<file_name>` and spawns
+/// it as described in `LevelModule`, at its world position. Levels saved in separate files are
+/// read from their `externalRelPath`.
+///
+/// Entities are placed at their pivot with a `Collider` rect of their size, and their fields
+/// become properties. The entity tiles shown in the editor are not drawn. Each IntGrid value
+/// becomes one `Collider` entity classed by the value's identifier (the layer's if it has none)
+/// with a "value" property. Tiles outside their tileset are skipped and listed in
+/// `Level::warnings`.
+pub fn load_ldtk(
+    world: &World,
+    gpu_device: *mut SDL_GPUDevice,
+    file_name: &str,
+    level: &str
+) -> Result<Entity, String> {
+    let path = PathBuf::from(format!("{}/Levels/{}", BASE_PATH, file_name));
+    let project = read_json(&path)?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let level_value = project["levels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|value| value["identifier"].as_str() == Some(level))
+        .ok_or_else(|| format!("{}: no level {}", path.display(), level))?;
+    let external;
+    let level_value = match level_value["externalRelPath"].as_str() {
+        Some(relative_path) => {
+            external = read_json(&directory.join(relative_path))?;
+            &external
+        }
+        None => level_value,
+    };
+
+    let defs = &project["defs"];
+    let mut textures = HashMap::new();
+    let mut tilesets = HashMap::new();
+    for value in defs["tilesets"].as_array().into_iter().flatten() {
+        // Tilesets without a path are LDtk's embedded icons
+        let Some(relative_path) = value["relPath"].as_str() else {
+            continue;
+        };
+        let tileset = load_tileset(gpu_device, value, &directory.join(relative_path), &mut textures)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        tilesets.insert(value["uid"].as_i64().unwrap_or_default(), tileset);
+    }
+
+    let layers = level_value["layerInstances"]
+        .as_array()
+        .ok_or_else(|| format!("{}: level {} has no layers", path.display(), level))?;
+    for layer in layers {
+        let (width, height) = (json_u32(&layer["__cWid"]), json_u32(&layer["__cHei"]));
+        if !width.checked_mul(height).is_some_and(|cells| cells <= MAX_LAYER_CELLS) {
+            return Err(
+                format!(
+                    "{}: layer {} is too large with {}x{} cells",
+                    path.display(),
+                    layer["__identifier"].as_str().unwrap_or_default(),
+                    width,
+                    height
+                )
+            );
+        }
+    }
+
+    // The first layer is the top one
+    let mut warnings = Vec::new();
+    let tiles_per_layer: Vec<Option<(Tileset, Vec<PlacedTile>)>> = layers
+        .iter()
+        .rev()
+        .map(|layer| layer_tiles(layer, &tilesets, &mut warnings))
+        .collect();
+
+    let origin = Vec2::new(json_f32(&level_value["worldX"]), json_f32(&level_value["worldY"]));
+    let root = spawn_level(
+        world,
+        level,
+        Vec2::new(json_f32(&level_value["pxWid"]), json_f32(&level_value["pxHei"])),
+        origin,
+        LevelObject {
+            name: level.to_owned(),
+            properties: field_properties(&level_value["fieldInstances"]),
+            ..Default::default()
+        },
+        warnings
+    );
+
+    for (index, (layer, tiles)) in layers.iter().rev().zip(tiles_per_layer).enumerate() {
+        let z = LAYER_Z_START + (index as f32) * LAYER_Z_STEP;
+        let name = layer["__identifier"].as_str().unwrap_or_default();
+        let grid_size = json_f32(&layer["__gridSize"]).max(1.0);
+        let size = (json_u32(&layer["__cWid"]), json_u32(&layer["__cHei"]));
+        let offset = Vec2::new(json_f32(&layer["__pxTotalOffsetX"]), json_f32(&layer["__pxTotalOffsetY"]));
+
+        if let Some((tileset, tiles)) = tiles {
+            // Tile layers are not moved by the root's `Transform2D` like the other children
+            let cell_size = Vec2::splat(grid_size);
+            let position = (origin + offset).extend(z);
+            for tile_layer in build_tile_layers(size, cell_size, position, &[tileset], &tiles) {
+                spawn_tile_layer(world, root, name, tile_layer);
+            }
+        }
+
+        match layer["__type"].as_str().unwrap_or_default() {
+            "Entities" => {
+                for entity in layer["entityInstances"].as_array().into_iter().flatten() {
+                    let identifier = entity["__identifier"].as_str().unwrap_or_default();
+                    let size = Vec2::new(json_f32(&entity["width"]), json_f32(&entity["height"]));
+                    let pivot = Vec2::new(json_f32(&entity["__pivot"][0]), json_f32(&entity["__pivot"][1]));
+                    let position = offset + Vec2::new(json_f32(&entity["px"][0]), json_f32(&entity["px"][1]));
+
+                    spawn_object(
+                        world,
+                        root,
+                        LevelObject {
+                            name: identifier.to_owned(),
+                            class: identifier.to_owned(),
+                            properties: field_properties(&entity["fieldInstances"]),
+                        },
+                        position,
+                        0.0,
+                        vec![Shape::Rect { position: -pivot * size, size }]
+                    );
+                }
+            }
+            "IntGrid" => {
+                let layer_uid = layer["layerDefUid"].as_i64();
+                let definition = defs["layers"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|definition| definition["uid"].as_i64() == layer_uid);
+                let cells: Vec<i64> = layer["intGridCsv"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|value| value.as_i64().unwrap_or_default())
+                    .collect();
+
+                for (value, shapes) in int_grid_shapes(&cells, size.0, grid_size, offset) {
+                    let class = definition
+                        .and_then(|definition| {
+                            definition["intGridValues"]
+                                .as_array()
+                                .into_iter()
+                                .flatten()
+                                .find(|grid_value| grid_value["value"].as_i64() == Some(value))
+                        })
+                        .and_then(|grid_value| grid_value["identifier"].as_str())
+                        .unwrap_or(name);
+
+                    let object = LevelObject {
+                        name: name.to_owned(),
+                        class: class.to_owned(),
+                        properties: HashMap::from([("value".to_owned(), PropertyValue::Int(value))]),
+                    };
+                    spawn_collider(world, root, object, shapes);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(root)
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    serde_json::from_str(&text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn json_f32(value: &Value) -> f32 {
+    value.as_f64().unwrap_or_default() as f32
+}
+
+fn json_u32(value: &Value) -> u32 {
+    value.as_u64().unwrap_or_default() as u32
+}
+
+/// The tileset and tiles of a visible tile layer, skipping tiles outside the tileset.
+fn layer_tiles(
+    layer: &Value,
+    tilesets: &HashMap<i64, Tileset>,
+    warnings: &mut Vec<String>
+) -> Option<(Tileset, Vec<PlacedTile>)> {
+    let tileset = *layer["__tilesetDefUid"].as_i64().and_then(|uid| tilesets.get(&uid))?;
+    if layer["visible"].as_bool() == Some(false) {
+        return None;
+    }
+    let grid_size = json_f32(&layer["__gridSize"]).max(1.0);
+
+    let mut tiles = Vec::new();
+    let mut skipped = 0;
+    for tile in layer["gridTiles"].as_array().into_iter().chain(layer["autoLayerTiles"].as_array()).flatten() {
+        let id = tile["t"].as_u64().unwrap_or_default();
+        if id >= u64::from(tileset.tile_count) {
+            skipped += 1;
+            continue;
+        }
+
+        let flip = tile["f"].as_u64().unwrap_or_default();
+        let mut flags = 0;
+        if flip & 1 != 0 {
+            flags |= TILE_FLIP_HORIZONTAL;
+        }
+        if flip & 2 != 0 {
+            flags |= TILE_FLIP_VERTICAL;
+        }
+
+        tiles.push(PlacedTile {
+            x: (json_f32(&tile["px"][0]) / grid_size) as u32,
+            y: (json_f32(&tile["px"][1]) / grid_size) as u32,
+            tileset: 0,
+            tile: ((id as u32) + 1) | flags,
+        });
+    }
+    if skipped > 0 {
+        let name = layer["__identifier"].as_str().unwrap_or_default();
+        warnings.push(format!("layer {} has {} tiles outside its tileset", name, skipped));
+    }
+
+    Some((tileset, tiles))
+}
+
+fn load_tileset(
+    gpu_device: *mut SDL_GPUDevice,
+    value: &Value,
+    image_path: &Path,
+    textures: &mut HashMap<PathBuf, (*mut SDL_GPUTexture, Vec2)>
+) -> Result<Tileset, String> {
+    let (texture, _) = load_tileset_texture(gpu_device, image_path, textures)?;
+
+    Tileset::new(
+        texture,
+        Vec2::new(json_f32(&value["pxWid"]), json_f32(&value["pxHei"])),
+        Vec2::splat(json_f32(&value["tileGridSize"])),
+        json_f32(&value["padding"]),
+        json_f32(&value["spacing"])
+    )
+}
+
+/// One rect per horizontal run of equal cells, grouped by value, in level space.
+fn int_grid_shapes(cells: &[i64], width: u32, grid_size: f32, offset: Vec2) -> Vec<(i64, Vec<Shape>)> {
+    let mut shapes: Vec<(i64, Vec<Shape>)> = Vec::new();
+    if width == 0 {
+        return shapes;
+    }
+
+    for (y, row) in cells.chunks(width as usize).enumerate() {
+        let mut x = 0;
+        while x < row.len() {
+            let value = row[x];
+            let start = x;
+            while x < row.len() && row[x] == value {
+                x += 1;
+            }
+            if value == 0 {
+                continue;
+            }
+
+            let rect = Shape::Rect {
+                position: offset + Vec2::new(start as f32, y as f32) * grid_size,
+                size: Vec2::new((x - start) as f32, 1.0) * grid_size,
+            };
+            match shapes.iter_mut().find(|(shapes_value, _)| *shapes_value == value) {
+                Some((_, value_shapes)) => value_shapes.push(rect),
+                None => shapes.push((value, vec![rect])),
+            }
+        }
+    }
+
+    shapes
+}
+
+fn field_properties(value: &Value) -> HashMap<String, PropertyValue> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|field| {
+            let name = field["__identifier"].as_str()?.to_owned();
+            let value = match (&field["__value"], field["__type"].as_str().unwrap_or_default()) {
+                (Value::Null, _) => {
+                    return None;
+                }
+                (Value::Bool(value), _) => PropertyValue::Bool(*value),
+                (Value::Number(number), "Float") => PropertyValue::Float(number.as_f64().unwrap_or_default()),
+                (Value::Number(number), _) => {
+                    match number.as_i64() {
+                        Some(value) => PropertyValue::Int(value),
+                        None => PropertyValue::Float(number.as_f64().unwrap_or_default()),
+                    }
+                }
+                (Value::String(value), _) => PropertyValue::String(value.clone()),
+                // Points, entity references and arrays keep their JSON
+                (value, _) => PropertyValue::String(value.to_string()),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
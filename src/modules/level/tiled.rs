@@ -0,0 +1,723 @@
+use std::{ collections::HashMap, io::Read, path::{ Path, PathBuf } };
+
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use flate2::read::{ GzDecoder, ZlibDecoder };
+use flecs_ecs::core::{ Entity, World };
+use glam::Vec2;
+use roxmltree::Node;
+use sdl3_sys::gpu::{ SDL_GPUDevice, SDL_GPUTexture };
+use serde_json::Value;
+
+use crate::{
+    modules::{
+        level::{
+            build_tile_layers,
+            load_tileset_texture,
+            spawn_collider,
+            spawn_level,
+            spawn_object,
+            spawn_tile_layer,
+            LevelObject,
+            PlacedTile,
+            PropertyValue,
+            Shape,
+            LAYER_Z_START,
+            LAYER_Z_STEP,
+        },
+        tilemap::{ TileLayer, Tileset, TILE_FLAGS },
+    },
+    BASE_PATH,
+};
+
+/// Tile ids without the flip flags, the fourth flag bit (hexagonal rotation) is dropped too.
+const GID_MASK: u32 = 0x0fff_ffff;
+
+type TextureCache = HashMap<PathBuf, (*mut SDL_GPUTexture, Vec2)>;
+
+struct TiledTileset {
+    name: String,
+    first_gid: u32,
+    /// None for image collection tilesets, whose tiles are not drawn.
+    tileset: Option<Tileset>,
+    /// Collision shapes by tile id, relative to the tile's top left corner.
+    shapes: HashMap<u32, Vec<Shape>>,
+}
+
+enum ObjectKind {
+    Rect,
+    Ellipse,
+    Point,
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+}
+
+struct TiledObject {
+    object: LevelObject,
+    position: Vec2,
+    /// In radians.
+    rotation: f32,
+    size: Vec2,
+    shapes: Vec<Shape>,
+    /// Set for tile objects.
+    gid: Option<u32>,
+}
+
+enum TiledLayer {
+    Tiles {
+        name: String,
+        width: u32,
+        height: u32,
+        offset: Vec2,
+        visible: bool,
+        gids: Vec<u32>,
+    },
+    Objects {
+        offset: Vec2,
+        objects: Vec<TiledObject>,
+    },
+}
+
+struct TiledMap {
+    width: u32,
+    height: u32,
+    tile_size: Vec2,
+    properties: HashMap<String, PropertyValue>,
+    tilesets: Vec<TiledTileset>,
+    /// Groups flattened, in drawing order.
+    layers: Vec<TiledLayer>,
+}
+
+/// Loads a Tiled map from `Levels/<file_name>`, saved as `.tmx` or `.tmj`/`.json`, and spawns
+/// it as described in `LevelModule`. Only finite orthogonal maps are supported. Tile data may be
+/// CSV or base64, uncompressed or with zlib or gzip compression. External tilesets (`.tsx`,
+/// `.tsj`) and their images are resolved relative to the file referencing them.
+///
+/// Tile collision shapes ignore flipped tiles, and tile objects are drawn without their rotation.
+/// Tiles of image collection tilesets are not drawn, such tilesets are listed in
+/// `Level::warnings`.
+pub fn load_tiled(world: &World, gpu_device: *mut SDL_GPUDevice, file_name: &str) -> Result<Entity, String> {
+    let path = PathBuf::from(format!("{}/Levels/{}", BASE_PATH, file_name));
+    let text = read_file(&path)?;
+    let mut textures = TextureCache::new();
+
+    let map = if file_name.ends_with(".tmx") {
+        parse_tmx(gpu_device, &path, &text, &mut textures)
+    } else {
+        parse_tmj(gpu_device, &path, &text, &mut textures)
+    };
+    let map = map.map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    Ok(spawn_map(world, file_name, map))
+}
+
+fn read_file(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn spawn_map(world: &World, name: &str, map: TiledMap) -> Entity {
+    let TiledMap { width, height, tile_size, properties, tilesets, layers } = map;
+    let warnings = tilesets
+        .iter()
+        .filter(|tiled| tiled.tileset.is_none())
+        .map(|tiled| format!("tileset {} has no single image, its tiles are not drawn", tiled.name))
+        .collect();
+
+    // Drawable tilesets in one list for `build_tile_layers`, with the index of each in it
+    let mut drawable = Vec::new();
+    let drawable_index: Vec<Option<usize>> = tilesets
+        .iter()
+        .map(|tiled| {
+            tiled.tileset.map(|tileset| {
+                drawable.push(tileset);
+                drawable.len() - 1
+            })
+        })
+        .collect();
+
+    // `(tileset, tile id in it, flags)` of a global tile id
+    let resolve = |gid: u32| -> Option<(usize, u32, u32)> {
+        let id = gid & GID_MASK;
+        if id == 0 {
+            return None;
+        }
+        let tileset = tilesets.iter().rposition(|tiled| tiled.first_gid <= id)?;
+        Some((tileset, id - tilesets[tileset].first_gid, gid & TILE_FLAGS))
+    };
+
+    let root = spawn_level(
+        world,
+        name,
+        Vec2::new(width as f32, height as f32) * tile_size,
+        Vec2::ZERO,
+        LevelObject {
+            name: name.to_owned(),
+            properties,
+            ..Default::default()
+        },
+        warnings
+    );
+
+    for (index, layer) in layers.into_iter().enumerate() {
+        let z = LAYER_Z_START + (index as f32) * LAYER_Z_STEP;
+
+        match layer {
+            TiledLayer::Tiles { name, width, height, offset, visible, gids } => {
+                let mut placed = Vec::new();
+                let mut shapes = Vec::new();
+                for (cell, gid) in gids.into_iter().enumerate() {
+                    let Some((tileset, id, flags)) = resolve(gid) else {
+                        continue;
+                    };
+                    let (x, y) = ((cell as u32) % width, (cell as u32) / width);
+
+                    if let Some(tile_shapes) = tilesets[tileset].shapes.get(&id) {
+                        // Tiles taller than a cell stick out of its top, like Tiled draws them
+                        let tile_height = tilesets[tileset].tileset.map_or(tile_size.y, |tileset| tileset.tile_size.y);
+                        let corner =
+                            offset + Vec2::new(x as f32, (y + 1) as f32) * tile_size - Vec2::new(0.0, tile_height);
+                        shapes.extend(tile_shapes.iter().map(|shape| shape.translated(corner)));
+                    }
+                    if let Some(tileset) = drawable_index[tileset] {
+                        placed.push(PlacedTile { x, y, tileset, tile: (id + 1) | flags });
+                    }
+                }
+
+                // Hidden layers still collide, e.g. a layer painted only for collision
+                if visible {
+                    for layer in build_tile_layers((width, height), tile_size, offset.extend(z), &drawable, &placed) {
+                        spawn_tile_layer(world, root, &name, layer);
+                    }
+                }
+                spawn_collider(world, root, LevelObject { name, ..Default::default() }, shapes);
+            }
+            TiledLayer::Objects { offset, objects } => {
+                for object in objects {
+                    let position = offset + object.position;
+                    let entity = spawn_object(world, root, object.object, position, object.rotation, object.shapes);
+
+                    let Some((tileset, id, flags)) = object.gid.and_then(resolve) else {
+                        continue;
+                    };
+                    let Some(tileset) = drawable_index[tileset] else {
+                        continue;
+                    };
+                    // Tile objects are anchored at their bottom left corner
+                    let mut layer = TileLayer::new(
+                        drawable[tileset],
+                        1,
+                        1,
+                        object.size,
+                        (position - Vec2::new(0.0, object.size.y)).extend(z)
+                    );
+                    layer.set(0, 0, (id + 1) | flags);
+                    world.entity_from_id(entity).set(layer);
+                }
+            }
+        }
+    }
+
+    root
+}
+
+/// Shapes of an object relative to its position.
+fn object_shapes(kind: ObjectKind, size: Vec2, tile: bool) -> Vec<Shape> {
+    match kind {
+        // Tile objects hang up from their position
+        ObjectKind::Rect if tile => vec![Shape::Rect { position: Vec2::new(0.0, -size.y), size }],
+        ObjectKind::Rect if size == Vec2::ZERO => Vec::new(),
+        ObjectKind::Rect => vec![Shape::Rect { position: Vec2::ZERO, size }],
+        ObjectKind::Ellipse => vec![Shape::Ellipse { center: size / 2.0, radii: size / 2.0 }],
+        ObjectKind::Point => vec![Shape::Point(Vec2::ZERO)],
+        ObjectKind::Polygon(points) => vec![Shape::Polygon(points)],
+        ObjectKind::Polyline(points) => vec![Shape::Polyline(points)],
+    }
+}
+
+/// Shapes of a tile's collision objects, relative to the tile.
+fn tile_shapes(objects: Vec<TiledObject>) -> Vec<Shape> {
+    objects
+        .into_iter()
+        .flat_map(|object| {
+            let position = object.position;
+            object.shapes.into_iter().map(move |shape| shape.translated(position))
+        })
+        .collect()
+}
+
+fn tileset_from_image(
+    gpu_device: *mut SDL_GPUDevice,
+    image_path: &Path,
+    tile_size: Vec2,
+    margin: f32,
+    spacing: f32,
+    textures: &mut TextureCache
+) -> Result<Tileset, String> {
+    let (texture, image_size) = load_tileset_texture(gpu_device, image_path, textures)?;
+    Tileset::new(texture, image_size, tile_size, margin, spacing)
+}
+
+/// Loads an external `.tsx` or `.tsj` tileset.
+fn load_external_tileset(
+    gpu_device: *mut SDL_GPUDevice,
+    path: &Path,
+    first_gid: u32,
+    textures: &mut TextureCache
+) -> Result<TiledTileset, String> {
+    let text = read_file(path)?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let tileset = if path.extension().is_some_and(|extension| extension == "tsx") {
+        let document = roxmltree::Document::parse(&text).map_err(|error| error.to_string());
+        document.and_then(|document| xml_tileset(gpu_device, document.root_element(), first_gid, directory, textures))
+    } else {
+        let json = serde_json::from_str::<Value>(&text).map_err(|error| error.to_string());
+        json.and_then(|json| json_tileset(gpu_device, &json, first_gid, directory, textures))
+    };
+    tileset.map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+/// Decodes the tile ids of a layer's data.
+fn decode_data(text: &str, encoding: &str, compression: Option<&str>) -> Result<Vec<u32>, String> {
+    match encoding {
+        "csv" => text
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<u32>().map_err(|error| format!("tile {}: {}", value, error)))
+            .collect(),
+        "base64" => {
+            let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+            let bytes = STANDARD.decode(text).map_err(|error| error.to_string())?;
+
+            let mut decompressed = Vec::new();
+            let bytes = match compression.unwrap_or_default() {
+                "" => bytes,
+                "zlib" => {
+                    ZlibDecoder::new(&bytes[..]).read_to_end(&mut decompressed).map_err(|error| error.to_string())?;
+                    decompressed
+                }
+                "gzip" => {
+                    GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed).map_err(|error| error.to_string())?;
+                    decompressed
+                }
+                compression => {
+                    return Err(format!("{} compression is not supported", compression));
+                }
+            };
+
+            Ok(
+                bytes
+                    .chunks_exact(4)
+                    .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                    .collect()
+            )
+        }
+        encoding => Err(format!("{} encoding is not supported", encoding)),
+    }
+}
+
+/// Rejects tile layers whose data does not fill exactly `width` by `height` cells.
+fn check_layer(name: &str, width: u32, height: u32, gids: &[u32]) -> Result<(), String> {
+    if width == 0 || height == 0 || gids.len() != (width as usize) * (height as usize) {
+        return Err(format!("layer {} has {} tiles for {}x{} cells", name, gids.len(), width, height));
+    }
+    Ok(())
+}
+
+fn property_value(kind: &str, value: &str) -> PropertyValue {
+    match kind {
+        "bool" => PropertyValue::Bool(value == "true"),
+        // Object properties hold the referenced object's id
+        "int" | "object" => PropertyValue::Int(value.parse().unwrap_or_default()),
+        "float" => PropertyValue::Float(value.parse().unwrap_or_default()),
+        _ => PropertyValue::String(value.to_owned()),
+    }
+}
+
+fn check_map(orientation: Option<&str>, infinite: bool) -> Result<(), String> {
+    if orientation != Some("orthogonal") {
+        return Err(format!("{} maps are not supported", orientation.unwrap_or("unknown")));
+    }
+    if infinite {
+        return Err("infinite maps are not supported".to_owned());
+    }
+    Ok(())
+}
+
+// TMX
+
+fn attribute<T: std::str::FromStr>(node: Node, name: &str) -> Option<T> {
+    node.attribute(name)?.parse().ok()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(tag))
+}
+
+fn children<'a, 'input: 'a>(node: Node<'a, 'input>, tag: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+fn parse_tmx(
+    gpu_device: *mut SDL_GPUDevice,
+    path: &Path,
+    text: &str,
+    textures: &mut TextureCache
+) -> Result<TiledMap, String> {
+    let document = roxmltree::Document::parse(text).map_err(|error| error.to_string())?;
+    let map = document.root_element();
+    check_map(map.attribute("orientation"), map.attribute("infinite") == Some("1"))?;
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut tilesets = Vec::new();
+    for node in children(map, "tileset") {
+        let first_gid = attribute(node, "firstgid").unwrap_or(1);
+        tilesets.push(match node.attribute("source") {
+            Some(source) => load_external_tileset(gpu_device, &directory.join(source), first_gid, textures)?,
+            None => xml_tileset(gpu_device, node, first_gid, directory, textures)?,
+        });
+    }
+
+    let mut layers = Vec::new();
+    xml_layers(map, Vec2::ZERO, &mut layers)?;
+
+    Ok(TiledMap {
+        width: attribute(map, "width").unwrap_or_default(),
+        height: attribute(map, "height").unwrap_or_default(),
+        tile_size: Vec2::new(
+            attribute(map, "tilewidth").unwrap_or_default(),
+            attribute(map, "tileheight").unwrap_or_default()
+        ),
+        properties: xml_properties(map),
+        tilesets,
+        layers,
+    })
+}
+
+fn xml_tileset(
+    gpu_device: *mut SDL_GPUDevice,
+    node: Node,
+    first_gid: u32,
+    directory: &Path,
+    textures: &mut TextureCache
+) -> Result<TiledTileset, String> {
+    let tile_size = Vec2::new(
+        attribute(node, "tilewidth").unwrap_or_default(),
+        attribute(node, "tileheight").unwrap_or_default()
+    );
+
+    let tileset = match child(node, "image").and_then(|image| image.attribute("source")) {
+        Some(source) => {
+            Some(
+                tileset_from_image(
+                    gpu_device,
+                    &directory.join(source),
+                    tile_size,
+                    attribute(node, "margin").unwrap_or_default(),
+                    attribute(node, "spacing").unwrap_or_default(),
+                    textures
+                )?
+            )
+        }
+        None => None,
+    };
+
+    let mut shapes = HashMap::new();
+    for tile in children(node, "tile") {
+        let Some(group) = child(tile, "objectgroup") else {
+            continue;
+        };
+        let objects = children(group, "object").map(xml_object).collect();
+        shapes.insert(attribute(tile, "id").unwrap_or_default(), tile_shapes(objects));
+    }
+
+    Ok(TiledTileset {
+        name: node.attribute("name").unwrap_or_default().to_owned(),
+        first_gid,
+        tileset,
+        shapes,
+    })
+}
+
+fn xml_layers(node: Node, offset: Vec2, layers: &mut Vec<TiledLayer>) -> Result<(), String> {
+    for layer in node.children().filter(Node::is_element) {
+        let offset = offset + Vec2::new(
+            attribute(layer, "offsetx").unwrap_or_default(),
+            attribute(layer, "offsety").unwrap_or_default()
+        );
+
+        match layer.tag_name().name() {
+            "layer" => {
+                let name = layer.attribute("name").unwrap_or_default();
+                let data = child(layer, "data").ok_or_else(|| format!("layer {} has no data", name))?;
+                let gids = match data.attribute("encoding") {
+                    Some(encoding) => {
+                        decode_data(data.text().unwrap_or_default(), encoding, data.attribute("compression"))?
+                    }
+                    None => children(data, "tile")
+                        .map(|tile| attribute(tile, "gid").unwrap_or_default())
+                        .collect(),
+                };
+
+                let (width, height) = (
+                    attribute(layer, "width").unwrap_or_default(),
+                    attribute(layer, "height").unwrap_or_default(),
+                );
+                check_layer(name, width, height, &gids)?;
+
+                layers.push(TiledLayer::Tiles {
+                    name: name.to_owned(),
+                    width,
+                    height,
+                    offset,
+                    visible: layer.attribute("visible") != Some("0"),
+                    gids,
+                });
+            }
+            "objectgroup" => {
+                layers.push(TiledLayer::Objects {
+                    offset,
+                    objects: children(layer, "object").map(xml_object).collect(),
+                });
+            }
+            "group" => xml_layers(layer, offset, layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn xml_points(node: Node) -> Vec<Vec2> {
+    node.attribute("points")
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|point| {
+            let (x, y) = point.split_once(',')?;
+            Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect()
+}
+
+fn xml_object(node: Node) -> TiledObject {
+    let size = Vec2::new(attribute(node, "width").unwrap_or_default(), attribute(node, "height").unwrap_or_default());
+    let kind = if child(node, "ellipse").is_some() {
+        ObjectKind::Ellipse
+    } else if child(node, "point").is_some() {
+        ObjectKind::Point
+    } else if let Some(polygon) = child(node, "polygon") {
+        ObjectKind::Polygon(xml_points(polygon))
+    } else if let Some(polyline) = child(node, "polyline") {
+        ObjectKind::Polyline(xml_points(polyline))
+    } else {
+        ObjectKind::Rect
+    };
+    let gid = attribute(node, "gid");
+
+    TiledObject {
+        object: LevelObject {
+            name: node.attribute("name").unwrap_or_default().to_owned(),
+            // "type" before Tiled 1.9
+            class: node.attribute("class").or(node.attribute("type")).unwrap_or_default().to_owned(),
+            properties: xml_properties(node),
+        },
+        position: Vec2::new(attribute(node, "x").unwrap_or_default(), attribute(node, "y").unwrap_or_default()),
+        rotation: attribute::<f32>(node, "rotation").unwrap_or_default().to_radians(),
+        size,
+        shapes: object_shapes(kind, size, gid.is_some()),
+        gid,
+    }
+}
+
+fn xml_properties(node: Node) -> HashMap<String, PropertyValue> {
+    let Some(properties) = child(node, "properties") else {
+        return HashMap::new();
+    };
+
+    children(properties, "property")
+        .map(|property| {
+            // Multiline strings are stored as text instead of in "value"
+            let value = property.attribute("value").or(property.text()).unwrap_or_default();
+            (
+                property.attribute("name").unwrap_or_default().to_owned(),
+                property_value(property.attribute("type").unwrap_or("string"), value),
+            )
+        })
+        .collect()
+}
+
+// JSON
+
+fn json_f32(value: &Value) -> f32 {
+    value.as_f64().unwrap_or_default() as f32
+}
+
+fn json_u32(value: &Value) -> u32 {
+    value.as_u64().unwrap_or_default() as u32
+}
+
+fn parse_tmj(
+    gpu_device: *mut SDL_GPUDevice,
+    path: &Path,
+    text: &str,
+    textures: &mut TextureCache
+) -> Result<TiledMap, String> {
+    let json: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    check_map(json["orientation"].as_str(), json["infinite"].as_bool() == Some(true))?;
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut tilesets = Vec::new();
+    for value in json["tilesets"].as_array().into_iter().flatten() {
+        let first_gid = value["firstgid"].as_u64().unwrap_or(1) as u32;
+        tilesets.push(match value["source"].as_str() {
+            Some(source) => load_external_tileset(gpu_device, &directory.join(source), first_gid, textures)?,
+            None => json_tileset(gpu_device, value, first_gid, directory, textures)?,
+        });
+    }
+
+    let mut layers = Vec::new();
+    json_layers(&json["layers"], Vec2::ZERO, &mut layers)?;
+
+    Ok(TiledMap {
+        width: json_u32(&json["width"]),
+        height: json_u32(&json["height"]),
+        tile_size: Vec2::new(json_f32(&json["tilewidth"]), json_f32(&json["tileheight"])),
+        properties: json_properties(&json["properties"]),
+        tilesets,
+        layers,
+    })
+}
+
+fn json_tileset(
+    gpu_device: *mut SDL_GPUDevice,
+    value: &Value,
+    first_gid: u32,
+    directory: &Path,
+    textures: &mut TextureCache
+) -> Result<TiledTileset, String> {
+    let tile_size = Vec2::new(json_f32(&value["tilewidth"]), json_f32(&value["tileheight"]));
+
+    let tileset = match value["image"].as_str() {
+        Some(image) => {
+            Some(
+                tileset_from_image(
+                    gpu_device,
+                    &directory.join(image),
+                    tile_size,
+                    json_f32(&value["margin"]),
+                    json_f32(&value["spacing"]),
+                    textures
+                )?
+            )
+        }
+        None => None,
+    };
+
+    let mut shapes = HashMap::new();
+    for tile in value["tiles"].as_array().into_iter().flatten() {
+        let Some(objects) = tile["objectgroup"]["objects"].as_array() else {
+            continue;
+        };
+        shapes.insert(json_u32(&tile["id"]), tile_shapes(objects.iter().map(json_object).collect()));
+    }
+
+    Ok(TiledTileset {
+        name: value["name"].as_str().unwrap_or_default().to_owned(),
+        first_gid,
+        tileset,
+        shapes,
+    })
+}
+
+fn json_layers(value: &Value, offset: Vec2, layers: &mut Vec<TiledLayer>) -> Result<(), String> {
+    for layer in value.as_array().into_iter().flatten() {
+        let offset = offset + Vec2::new(json_f32(&layer["offsetx"]), json_f32(&layer["offsety"]));
+
+        match layer["type"].as_str().unwrap_or_default() {
+            "tilelayer" => {
+                let gids = match &layer["data"] {
+                    Value::String(data) => decode_data(data, "base64", layer["compression"].as_str())?,
+                    data => data.as_array().into_iter().flatten().map(json_u32).collect(),
+                };
+
+                let name = layer["name"].as_str().unwrap_or_default();
+                let (width, height) = (json_u32(&layer["width"]), json_u32(&layer["height"]));
+                check_layer(name, width, height, &gids)?;
+
+                layers.push(TiledLayer::Tiles {
+                    name: name.to_owned(),
+                    width,
+                    height,
+                    offset,
+                    visible: layer["visible"].as_bool() != Some(false),
+                    gids,
+                });
+            }
+            "objectgroup" => {
+                layers.push(TiledLayer::Objects {
+                    offset,
+                    objects: layer["objects"].as_array().into_iter().flatten().map(json_object).collect(),
+                });
+            }
+            "group" => json_layers(&layer["layers"], offset, layers)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn json_points(value: &Value) -> Vec<Vec2> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|point| Vec2::new(json_f32(&point["x"]), json_f32(&point["y"])))
+        .collect()
+}
+
+fn json_object(value: &Value) -> TiledObject {
+    let size = Vec2::new(json_f32(&value["width"]), json_f32(&value["height"]));
+    let kind = if value["ellipse"].as_bool() == Some(true) {
+        ObjectKind::Ellipse
+    } else if value["point"].as_bool() == Some(true) {
+        ObjectKind::Point
+    } else if value["polygon"].is_array() {
+        ObjectKind::Polygon(json_points(&value["polygon"]))
+    } else if value["polyline"].is_array() {
+        ObjectKind::Polyline(json_points(&value["polyline"]))
+    } else {
+        ObjectKind::Rect
+    };
+    let gid = value["gid"].as_u64().map(|gid| gid as u32);
+
+    TiledObject {
+        object: LevelObject {
+            name: value["name"].as_str().unwrap_or_default().to_owned(),
+            // "type" before Tiled 1.9
+            class: value["class"].as_str().or(value["type"].as_str()).unwrap_or_default().to_owned(),
+            properties: json_properties(&value["properties"]),
+        },
+        position: Vec2::new(json_f32(&value["x"]), json_f32(&value["y"])),
+        rotation: json_f32(&value["rotation"]).to_radians(),
+        size,
+        shapes: object_shapes(kind, size, gid.is_some()),
+        gid,
+    }
+}
+
+fn json_properties(value: &Value) -> HashMap<String, PropertyValue> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|property| {
+            let kind = property["type"].as_str().unwrap_or("string");
+            let value = match &property["value"] {
+                Value::String(value) => property_value(kind, value),
+                value => property_value(kind, &value.to_string()),
+            };
+            (property["name"].as_str().unwrap_or_default().to_owned(), value)
+        })
+        .collect()
+}
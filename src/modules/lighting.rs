@@ -0,0 +1,728 @@
+use std::{ ffi::c_void, ptr::null_mut };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PreStore }, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, QueryAPI, QueryBuilderImpl, SystemAPI },
+};
+use glam::{ Mat4, Vec2, Vec3 };
+use sdl3_sys::{
+    gpu::*,
+    pixels::{ SDL_FColor, SDL_PIXELFORMAT_ABGR8888 },
+    stdinc::SDL_memcpy,
+    surface::{ SDL_CreateSurface, SDL_DestroySurface },
+};
+
+use crate::{
+    camera::{ Camera, CameraView, MainCamera },
+    gpu::{ upload_texture, GpuApi, GpuFrame, RenderEvent, ShadersInitEvent, DEPTH_FORMAT },
+    load_shader,
+    modules::sprites::SpritesBuffer,
+};
+
+/// 2D lighting for sprites. Sprite normals are rendered into a normal buffer, lights are
+/// accumulated on top of the ambient light into a light map and the scene is multiplied by the
+/// light map after the sprites of the `MainCamera` were drawn. Other cameras are not lit.
+///
+/// Must be imported after `SpritesModule` so the light passes run after the sprite upload.
+#[derive(Component)]
+pub struct LightingModule;
+
+#[derive(Component, Clone, Copy)]
+pub struct PointLight {
+    pub position: Vec2,
+    /// Height of the light above the sprite plane, used for normal mapping.
+    pub height: f32,
+    pub radius: f32,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct SpotLight {
+    pub position: Vec2,
+    pub height: f32,
+    pub radius: f32,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Direction of the cone in radians.
+    pub direction: f32,
+    /// Half angles of the cone in radians, the light fades out between the two.
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct AmbientLight {
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+/// Axis-aligned box blocking light, in world space.
+#[derive(Component, Clone, Copy)]
+pub struct LightOccluder {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LightData {
+    position: Vec2,
+    height: f32,
+    radius: f32,
+    color: Vec3,
+    intensity: f32,
+    direction: Vec2,
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct OccluderData {
+    min: Vec2,
+    max: Vec2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LightUniform {
+    screen_size: Vec2,
+    occluder_count: u32,
+    padding: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CompositeUniform {
+    screen_size: Vec2,
+    padding: Vec2,
+}
+
+#[derive(Component)]
+pub struct LightingPipelines {
+    pub normal: *mut SDL_GPUGraphicsPipeline,
+    pub light: *mut SDL_GPUGraphicsPipeline,
+    pub composite: *mut SDL_GPUGraphicsPipeline,
+    pub sampler: *mut SDL_GPUSampler,
+    /// 1x1 flat normal bound when `SpritesBuffer` has no normal map.
+    pub flat_normal: *mut SDL_GPUTexture,
+}
+
+#[derive(Component)]
+pub struct LightingTargets {
+    pub normal_buffer: *mut SDL_GPUTexture,
+    pub light_map: *mut SDL_GPUTexture,
+    pub light_buffer: *mut SDL_GPUBuffer,
+    pub light_transfer_buffer: *mut SDL_GPUTransferBuffer,
+    pub occluder_buffer: *mut SDL_GPUBuffer,
+    pub occluder_transfer_buffer: *mut SDL_GPUTransferBuffer,
+    pub light_capacity: usize,
+    pub occluder_capacity: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+unsafe impl Send for LightingPipelines {}
+unsafe impl Sync for LightingPipelines {}
+
+unsafe impl Send for LightingTargets {}
+unsafe impl Sync for LightingTargets {}
+
+const NORMAL_BUFFER_FORMAT: SDL_GPUTextureFormat = SDL_GPU_TEXTUREFORMAT_R8G8B8A8_UNORM;
+const LIGHT_MAP_FORMAT: SDL_GPUTextureFormat = SDL_GPU_TEXTUREFORMAT_R16G16B16A16_FLOAT;
+
+impl PointLight {
+    pub fn new(position: Vec2, radius: f32, color: Vec3) -> Self {
+        Self {
+            position,
+            height: 32.0,
+            radius,
+            color,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl SpotLight {
+    pub fn new(position: Vec2, radius: f32, color: Vec3, direction: f32, angle: f32) -> Self {
+        Self {
+            position,
+            height: 32.0,
+            radius,
+            color,
+            intensity: 1.0,
+            direction,
+            inner_angle: angle * 0.75,
+            outer_angle: angle,
+        }
+    }
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self {
+            color: Vec3::ONE,
+            intensity: 0.2,
+        }
+    }
+}
+
+fn create_storage_buffers(
+    gpu_device: *mut SDL_GPUDevice,
+    size: usize
+) -> (*mut SDL_GPUBuffer, *mut SDL_GPUTransferBuffer) {
+    unsafe {
+        let transfer_buffer = SDL_CreateGPUTransferBuffer(
+            gpu_device,
+            &(SDL_GPUTransferBufferCreateInfo {
+                usage: SDL_GPU_TRANSFERBUFFERUSAGE_UPLOAD,
+                size: size as u32,
+                ..Default::default()
+            })
+        );
+
+        let buffer = SDL_CreateGPUBuffer(
+            gpu_device,
+            &(SDL_GPUBufferCreateInfo {
+                usage: SDL_GPU_BUFFERUSAGE_GRAPHICS_STORAGE_READ,
+                size: size as u32,
+                ..Default::default()
+            })
+        );
+
+        (buffer, transfer_buffer)
+    }
+}
+
+fn create_target(
+    gpu_device: *mut SDL_GPUDevice,
+    format: SDL_GPUTextureFormat,
+    width: u32,
+    height: u32
+) -> *mut SDL_GPUTexture {
+    unsafe {
+        SDL_CreateGPUTexture(
+            gpu_device,
+            &(SDL_GPUTextureCreateInfo {
+                r#type: SDL_GPU_TEXTURETYPE_2D,
+                format,
+                width,
+                height,
+                layer_count_or_depth: 1,
+                num_levels: 1,
+                usage: SDL_GPU_TEXTUREUSAGE_SAMPLER | SDL_GPU_TEXTUREUSAGE_COLOR_TARGET,
+                ..Default::default()
+            })
+        )
+    }
+}
+
+impl LightingTargets {
+    fn new(gpu_device: *mut SDL_GPUDevice) -> Self {
+        let (light_buffer, light_transfer_buffer) = create_storage_buffers(
+            gpu_device,
+            256 * size_of::<LightData>()
+        );
+        let (occluder_buffer, occluder_transfer_buffer) = create_storage_buffers(
+            gpu_device,
+            256 * size_of::<OccluderData>()
+        );
+
+        Self {
+            normal_buffer: null_mut(),
+            light_map: null_mut(),
+            light_buffer,
+            light_transfer_buffer,
+            occluder_buffer,
+            occluder_transfer_buffer,
+            light_capacity: 256,
+            occluder_capacity: 256,
+            width: 0,
+            height: 0,
+        }
+    }
+
+    fn resize(&mut self, gpu_device: *mut SDL_GPUDevice, width: u32, height: u32) {
+        if self.width == width && self.height == height && self.light_map != null_mut() {
+            return;
+        }
+
+        unsafe {
+            if self.normal_buffer != null_mut() {
+                SDL_ReleaseGPUTexture(gpu_device, self.normal_buffer);
+                SDL_ReleaseGPUTexture(gpu_device, self.light_map);
+            }
+        }
+
+        self.normal_buffer = create_target(gpu_device, NORMAL_BUFFER_FORMAT, width, height);
+        self.light_map = create_target(gpu_device, LIGHT_MAP_FORMAT, width, height);
+        self.width = width;
+        self.height = height;
+    }
+
+    fn reserve(&mut self, gpu_device: *mut SDL_GPUDevice, lights: usize, occluders: usize) {
+        unsafe {
+            if lights > self.light_capacity {
+                SDL_ReleaseGPUBuffer(gpu_device, self.light_buffer);
+                SDL_ReleaseGPUTransferBuffer(gpu_device, self.light_transfer_buffer);
+                self.light_capacity = lights.next_power_of_two();
+                (self.light_buffer, self.light_transfer_buffer) = create_storage_buffers(
+                    gpu_device,
+                    self.light_capacity * size_of::<LightData>()
+                );
+            }
+
+            if occluders > self.occluder_capacity {
+                SDL_ReleaseGPUBuffer(gpu_device, self.occluder_buffer);
+                SDL_ReleaseGPUTransferBuffer(gpu_device, self.occluder_transfer_buffer);
+                self.occluder_capacity = occluders.next_power_of_two();
+                (self.occluder_buffer, self.occluder_transfer_buffer) = create_storage_buffers(
+                    gpu_device,
+                    self.occluder_capacity * size_of::<OccluderData>()
+                );
+            }
+        }
+    }
+}
+
+fn create_flat_normal(gpu_device: *mut SDL_GPUDevice) -> *mut SDL_GPUTexture {
+    unsafe {
+        let image = SDL_CreateSurface(1, 1, SDL_PIXELFORMAT_ABGR8888);
+        let pixel: [u8; 4] = [128, 128, 255, 255];
+        SDL_memcpy((*image).pixels, pixel.as_ptr() as *const c_void, 4);
+
+        let texture = upload_texture(gpu_device, image);
+        SDL_DestroySurface(image);
+        texture
+    }
+}
+
+fn create_pipeline(
+    gpu_device: *mut SDL_GPUDevice,
+    format: SDL_GPUTextureFormat,
+    blend_state: SDL_GPUColorTargetBlendState,
+    shared_pass: bool,
+    sample_count: SDL_GPUSampleCount,
+    vertex_shader: *mut SDL_GPUShader,
+    fragment_shader: *mut SDL_GPUShader
+) -> *mut SDL_GPUGraphicsPipeline {
+    unsafe {
+        let pipeline_create_info = SDL_GPUGraphicsPipelineCreateInfo {
+            target_info: SDL_GPUGraphicsPipelineTargetInfo {
+                num_color_targets: 1,
+                color_target_descriptions: &(SDL_GPUColorTargetDescription {
+                    format,
+                    blend_state,
+                }),
+                // Pipelines used inside the shared render pass must match its depth target
+                depth_stencil_format: DEPTH_FORMAT,
+                has_depth_stencil_target: shared_pass,
+                ..Default::default()
+            },
+            multisample_state: SDL_GPUMultisampleState {
+                sample_count,
+                ..Default::default()
+            },
+            primitive_type: SDL_GPU_PRIMITIVETYPE_TRIANGLELIST,
+            vertex_shader,
+            fragment_shader,
+            ..Default::default()
+        };
+
+        let pipeline = SDL_CreateGPUGraphicsPipeline(gpu_device, &pipeline_create_info);
+        if pipeline == null_mut() {
+            panic!("Failed to create Lighting pipeline");
+        }
+
+        SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+        SDL_ReleaseGPUShader(gpu_device, fragment_shader);
+
+        pipeline
+    }
+}
+
+impl Module for LightingModule {
+    fn module(world: &World) {
+        world.component::<PointLight>();
+        world.component::<SpotLight>();
+        world.component::<AmbientLight>();
+        world.component::<LightOccluder>();
+        world.component::<LightingPipelines>();
+        world.component::<LightingTargets>();
+
+        world.set(AmbientLight::default());
+
+        world.get::<&GpuApi>(|gpu_api| {
+            world.set(LightingTargets::new(gpu_api.gpu_device));
+        });
+
+        observer!("init_lighting_shaders", world, ShadersInitEvent, flecs::Any).each_iter(
+            |it, _, _| {
+                let event = &*it.param();
+                let world = it.world();
+                let gpu_device = event.gpu_device;
+
+                // `(file name, samplers, uniform buffers, storage buffers)` of each pipeline's shaders
+                let shader_files = [
+                    ("texture.vert", 0, 1, 1),
+                    ("sprite_normal.frag", 2, 0, 0),
+                    ("light.vert", 0, 1, 1),
+                    ("light.frag", 1, 1, 2),
+                    ("fullscreen.vert", 0, 0, 0),
+                    ("light_composite.frag", 1, 1, 0),
+                ];
+                // Scenes stay unlit while there are no `LightingPipelines`
+                let mut shaders = Vec::with_capacity(shader_files.len());
+                for (file_name, samplers, uniform_buffers, storage_buffers) in shader_files {
+                    match load_shader(gpu_device, file_name, samplers, uniform_buffers, storage_buffers, 0) {
+                        Ok(shader) => shaders.push(shader),
+                        Err(error) => {
+                            for shader in shaders {
+                                unsafe {
+                                    SDL_ReleaseGPUShader(gpu_device, shader);
+                                }
+                            }
+                            println!("Lighting disabled: {}", error);
+                            return;
+                        }
+                    }
+                }
+
+                unsafe {
+                    let format = SDL_GetGPUSwapchainTextureFormat(gpu_device, event.window);
+
+                    let normal = create_pipeline(
+                        gpu_device,
+                        NORMAL_BUFFER_FORMAT,
+                        SDL_GPUColorTargetBlendState::default(),
+                        false,
+                        SDL_GPU_SAMPLECOUNT_1,
+                        shaders[0],
+                        shaders[1]
+                    );
+
+                    let light = create_pipeline(
+                        gpu_device,
+                        LIGHT_MAP_FORMAT,
+                        SDL_GPUColorTargetBlendState {
+                            src_color_blendfactor: SDL_GPU_BLENDFACTOR_ONE,
+                            dst_color_blendfactor: SDL_GPU_BLENDFACTOR_ONE,
+                            color_blend_op: SDL_GPU_BLENDOP_ADD,
+                            src_alpha_blendfactor: SDL_GPU_BLENDFACTOR_ONE,
+                            dst_alpha_blendfactor: SDL_GPU_BLENDFACTOR_ONE,
+                            alpha_blend_op: SDL_GPU_BLENDOP_ADD,
+                            enable_blend: true,
+                            ..Default::default()
+                        },
+                        false,
+                        SDL_GPU_SAMPLECOUNT_1,
+                        shaders[2],
+                        shaders[3]
+                    );
+
+                    let composite = create_pipeline(
+                        gpu_device,
+                        format,
+                        SDL_GPUColorTargetBlendState {
+                            src_color_blendfactor: SDL_GPU_BLENDFACTOR_DST_COLOR,
+                            dst_color_blendfactor: SDL_GPU_BLENDFACTOR_ZERO,
+                            color_blend_op: SDL_GPU_BLENDOP_ADD,
+                            src_alpha_blendfactor: SDL_GPU_BLENDFACTOR_ZERO,
+                            dst_alpha_blendfactor: SDL_GPU_BLENDFACTOR_ONE,
+                            alpha_blend_op: SDL_GPU_BLENDOP_ADD,
+                            enable_blend: true,
+                            ..Default::default()
+                        },
+                        true,
+                        event.sample_count,
+                        shaders[4],
+                        shaders[5]
+                    );
+
+                    let sampler = SDL_CreateGPUSampler(
+                        gpu_device,
+                        &(SDL_GPUSamplerCreateInfo {
+                            min_filter: SDL_GPU_FILTER_NEAREST,
+                            mag_filter: SDL_GPU_FILTER_NEAREST,
+                            mipmap_mode: SDL_GPU_SAMPLERMIPMAPMODE_NEAREST,
+                            address_mode_u: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                            address_mode_v: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                            address_mode_w: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                            ..Default::default()
+                        })
+                    );
+
+                    world.set(LightingPipelines {
+                        normal,
+                        light,
+                        composite,
+                        sampler,
+                        flat_normal: create_flat_normal(gpu_device),
+                    });
+
+                    println!("Setting Lighting Pipelines");
+                }
+            }
+        );
+
+        let point_lights = world.query::<&PointLight>().set_cached().build();
+        let spot_lights = world.query::<&SpotLight>().set_cached().build();
+        let occluders = world.query::<&LightOccluder>().set_cached().build();
+
+        // Records the normal and light passes on the frame command buffer before the shared
+        // render pass begins.
+        system!("lighting_pass", world, &GpuApi($), &GpuFrame($), &AmbientLight($), &SpritesBuffer($), &LightingPipelines($), &mut LightingTargets($))
+            .kind::<PreStore>()
+            .each_iter(move |it, _, (gpu_api, frame, ambient, sprite_buffer, pipelines, targets)| unsafe {
+                let gpu_device = gpu_api.gpu_device;
+                let cmd_buf = frame.command_buffer;
+                if frame.swapchain_texture == null_mut() {
+                    return;
+                }
+
+                // Lighting follows the main camera's view and viewport
+                let world = it.world();
+                let mut main_view = None;
+                if let Some(camera) = MainCamera::entity(&world) {
+                    let entity = camera.id();
+                    camera.get::<(&Camera, &CameraView)>(|(camera, view)| {
+                        main_view = Some((entity, camera.view_projection, view.viewport));
+                    });
+                }
+                let Some((main_camera, view_projection, viewport)) = main_view else {
+                    return;
+                };
+
+                targets.resize(gpu_device, frame.width, frame.height);
+
+                let mut lights: Vec<LightData> = Vec::new();
+                point_lights.each(|light| {
+                    lights.push(LightData {
+                        position: light.position,
+                        height: light.height,
+                        radius: light.radius,
+                        color: light.color,
+                        intensity: light.intensity,
+                        direction: Vec2::X,
+                        cos_inner: -1.0,
+                        cos_outer: -1.0,
+                    });
+                });
+                spot_lights.each(|light| {
+                    lights.push(LightData {
+                        position: light.position,
+                        height: light.height,
+                        radius: light.radius,
+                        color: light.color,
+                        intensity: light.intensity,
+                        direction: Vec2::from_angle(light.direction),
+                        cos_inner: light.inner_angle.cos(),
+                        cos_outer: light.outer_angle.cos(),
+                    });
+                });
+
+                let mut occluder_data: Vec<OccluderData> = Vec::new();
+                occluders.each(|occluder| {
+                    occluder_data.push(OccluderData {
+                        min: occluder.position,
+                        max: occluder.position + occluder.size,
+                    });
+                });
+
+                targets.reserve(gpu_device, lights.len(), occluder_data.len());
+
+                if !lights.is_empty() || !occluder_data.is_empty() {
+                    let copy_pass = SDL_BeginGPUCopyPass(cmd_buf);
+
+                    for (data, size, transfer_buffer, buffer) in [
+                        (
+                            lights.as_ptr() as *const c_void,
+                            lights.len() * size_of::<LightData>(),
+                            targets.light_transfer_buffer,
+                            targets.light_buffer,
+                        ),
+                        (
+                            occluder_data.as_ptr() as *const c_void,
+                            occluder_data.len() * size_of::<OccluderData>(),
+                            targets.occluder_transfer_buffer,
+                            targets.occluder_buffer,
+                        ),
+                    ] {
+                        if size == 0 {
+                            continue;
+                        }
+
+                        let data_ptr = SDL_MapGPUTransferBuffer(gpu_device, transfer_buffer, true);
+                        SDL_memcpy(data_ptr, data, size);
+                        SDL_UnmapGPUTransferBuffer(gpu_device, transfer_buffer);
+
+                        SDL_UploadToGPUBuffer(
+                            copy_pass,
+                            &(SDL_GPUTransferBufferLocation {
+                                transfer_buffer,
+                                offset: 0,
+                            }),
+                            &(SDL_GPUBufferRegion {
+                                buffer,
+                                offset: 0,
+                                size: size as u32,
+                            }),
+                            true
+                        );
+                    }
+
+                    SDL_EndGPUCopyPass(copy_pass);
+                }
+
+                // Normal pass
+                let mut color_target_info = SDL_GPUColorTargetInfo::default();
+                color_target_info.texture = targets.normal_buffer;
+                color_target_info.clear_color = SDL_FColor { r: 0.5, g: 0.5, b: 1.0, a: 0.0 };
+                color_target_info.load_op = SDL_GPU_LOADOP_CLEAR;
+                color_target_info.store_op = SDL_GPU_STOREOP_STORE;
+
+                let render_pass = SDL_BeginGPURenderPass(cmd_buf, &color_target_info, 1, null_mut());
+                SDL_SetGPUViewport(render_pass, &viewport);
+                // Only the sprites packed for the main camera, the others may be culled for it
+                if let Some(sprites) = sprite_buffer.camera(main_camera) {
+                    let normal_map = if sprite_buffer.normal_map != null_mut() {
+                        sprite_buffer.normal_map
+                    } else {
+                        pipelines.flat_normal
+                    };
+
+                    SDL_BindGPUGraphicsPipeline(render_pass, pipelines.normal);
+                    SDL_BindGPUVertexStorageBuffers(render_pass, 0, &sprite_buffer.data_buffer, 1);
+                    SDL_BindGPUFragmentSamplers(
+                        render_pass,
+                        0,
+                        [
+                            SDL_GPUTextureSamplerBinding {
+                                texture: sprite_buffer.texture,
+                                sampler: sprite_buffer.sampler,
+                            },
+                            SDL_GPUTextureSamplerBinding {
+                                texture: normal_map,
+                                sampler: sprite_buffer.sampler,
+                            },
+                        ].as_ptr(),
+                        2
+                    );
+                    SDL_PushGPUVertexUniformData(
+                        cmd_buf,
+                        0,
+                        &view_projection as *const _ as *const c_void,
+                        size_of::<Mat4>() as u32
+                    );
+                    SDL_DrawGPUPrimitives(
+                        render_pass,
+                        (sprites.count * 6) as u32,
+                        1,
+                        (sprites.first * 6) as u32,
+                        0
+                    );
+                }
+                SDL_EndGPURenderPass(render_pass);
+
+                // Light accumulation pass, starting from the ambient light
+                let mut color_target_info = SDL_GPUColorTargetInfo::default();
+                color_target_info.texture = targets.light_map;
+                color_target_info.clear_color = SDL_FColor {
+                    r: ambient.color.x * ambient.intensity,
+                    g: ambient.color.y * ambient.intensity,
+                    b: ambient.color.z * ambient.intensity,
+                    a: 1.0,
+                };
+                color_target_info.load_op = SDL_GPU_LOADOP_CLEAR;
+                color_target_info.store_op = SDL_GPU_STOREOP_STORE;
+
+                let render_pass = SDL_BeginGPURenderPass(cmd_buf, &color_target_info, 1, null_mut());
+                SDL_SetGPUViewport(render_pass, &viewport);
+                if !lights.is_empty() {
+                    SDL_BindGPUGraphicsPipeline(render_pass, pipelines.light);
+                    SDL_BindGPUVertexStorageBuffers(render_pass, 0, &targets.light_buffer, 1);
+                    SDL_BindGPUFragmentSamplers(
+                        render_pass,
+                        0,
+                        &(SDL_GPUTextureSamplerBinding {
+                            texture: targets.normal_buffer,
+                            sampler: pipelines.sampler,
+                        }),
+                        1
+                    );
+                    SDL_BindGPUFragmentStorageBuffers(
+                        render_pass,
+                        0,
+                        [targets.light_buffer, targets.occluder_buffer].as_ptr(),
+                        2
+                    );
+                    SDL_PushGPUVertexUniformData(
+                        cmd_buf,
+                        0,
+                        &view_projection as *const _ as *const c_void,
+                        size_of::<Mat4>() as u32
+                    );
+
+                    let uniform = LightUniform {
+                        screen_size: Vec2::new(frame.width as f32, frame.height as f32),
+                        occluder_count: occluder_data.len() as u32,
+                        padding: 0.0,
+                    };
+                    SDL_PushGPUFragmentUniformData(
+                        cmd_buf,
+                        0,
+                        &uniform as *const _ as *const c_void,
+                        size_of::<LightUniform>() as u32
+                    );
+                    SDL_DrawGPUPrimitives(render_pass, (lights.len() * 6) as u32, 1, 0, 0);
+                }
+                SDL_EndGPURenderPass(render_pass);
+            });
+
+        observer!("lighting_composite", world, RenderEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+
+            let is_main_camera = MainCamera::entity(&world)
+                .map_or(false, |camera| camera.id() == event.camera);
+            if !is_main_camera {
+                return;
+            }
+
+            world.get::<(Option<&LightingPipelines>, &LightingTargets)>(|(pipelines, targets)| unsafe {
+                let Some(pipelines) = pipelines else {
+                    return;
+                };
+                if targets.light_map == null_mut() {
+                    return;
+                }
+
+                SDL_BindGPUGraphicsPipeline(event.render_pass, pipelines.composite);
+                SDL_BindGPUFragmentSamplers(
+                    event.render_pass,
+                    0,
+                    &(SDL_GPUTextureSamplerBinding {
+                        texture: targets.light_map,
+                        sampler: pipelines.sampler,
+                    }),
+                    1
+                );
+
+                // The light map is sampled by pixel position so it lines up with any viewport
+                let uniform = CompositeUniform {
+                    screen_size: Vec2::new(targets.width as f32, targets.height as f32),
+                    padding: Vec2::ZERO,
+                };
+                SDL_PushGPUFragmentUniformData(
+                    event.command_buffer,
+                    0,
+                    &uniform as *const _ as *const c_void,
+                    size_of::<CompositeUniform>() as u32
+                );
+                SDL_DrawGPUPrimitives(event.render_pass, 3, 1, 0, 0);
+            });
+        });
+    }
+}
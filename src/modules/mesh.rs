@@ -0,0 +1,543 @@
+use std::{ ffi::c_void, ptr::null_mut };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PostUpdate }, TermBuilderImpl, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, QueryAPI, QueryBuilderImpl, SystemAPI },
+};
+use glam::{ Mat4, Quat, Vec2, Vec3, Vec4 };
+use sdl3_sys::{
+    gpu::*,
+    mouse::SDL_BUTTON_RIGHT,
+    pixels::SDL_PIXELFORMAT_ABGR8888,
+    stdinc::SDL_memcpy,
+    surface::{ SDL_CreateSurface, SDL_DestroySurface },
+};
+
+use crate::{
+    camera::{ Camera, CameraView, RenderLayers },
+    gpu::{
+        upload_buffer,
+        upload_texture,
+        GpuApi,
+        RenderEvent,
+        ShadersInitEvent,
+        DEPTH_FORMAT,
+    },
+    load_shader,
+    modules::{ input::{ InputMap, Mouse }, time::Time },
+};
+
+/// Depth-tested 3D meshes drawn into every camera whose layer mask matches their
+/// `RenderLayers`, in the same pass and depth buffer as the sprites.
+///
+/// Must be imported after `ScalingModule` so `FlyCamera` sees this frame's viewport, and before
+/// `SpritesModule` so opaque meshes are drawn before the transparent sprites.
+#[derive(Component)]
+pub struct MeshModule;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Vertex3D {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+}
+
+/// GPU vertex and index buffers, copies share the same buffers.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Mesh {
+    pub vertex_buffer: *mut SDL_GPUBuffer,
+    pub index_buffer: *mut SDL_GPUBuffer,
+    pub index_count: u32,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Transform3D {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+/// World matrix of a `Transform3D` inside a `ChildOf` hierarchy, recomputed at `PostUpdate`.
+/// Meshes without it are drawn with their `Transform3D` alone.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GlobalTransform3D(pub Mat4);
+
+/// Meshes without a `Material` are drawn white.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Material {
+    /// Multiplied with the texture.
+    pub base_color: Vec4,
+    /// Null samples `MeshPipeline::white_texture`.
+    pub texture: *mut SDL_GPUTexture,
+}
+
+/// The light meshes are shaded with.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MeshLight {
+    /// Direction the light travels in, world space.
+    pub direction: Vec3,
+    /// 0..1, how bright the faces turned away from the light are.
+    pub ambient: f32,
+}
+
+/// Perspective camera steered with the "fly" and "fly_vertical" actions, looking around while
+/// the right mouse button is held. Rebuilds the `Camera` on the same entity at `PostUpdate`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FlyCamera {
+    pub position: Vec3,
+    /// Radians around the y axis, 0 looks down +z.
+    pub yaw: f32,
+    /// Radians, positive looks up.
+    pub pitch: f32,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    /// World units per second.
+    pub speed: f32,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: f32,
+}
+
+#[derive(Component)]
+pub struct MeshPipeline {
+    pub pipeline: *mut SDL_GPUGraphicsPipeline,
+    pub sampler: *mut SDL_GPUSampler,
+    /// Bound for materials without a texture.
+    pub white_texture: *mut SDL_GPUTexture,
+}
+
+#[repr(C)]
+struct MeshVertexUniforms {
+    view_projection: Mat4,
+    model: Mat4,
+}
+
+#[repr(C)]
+struct MeshFragmentUniforms {
+    base_color: Vec4,
+    /// xyz: direction, w: ambient.
+    light: Vec4,
+}
+
+unsafe impl Send for Mesh {}
+unsafe impl Sync for Mesh {}
+
+unsafe impl Send for Material {}
+unsafe impl Sync for Material {}
+
+unsafe impl Send for MeshPipeline {}
+unsafe impl Sync for MeshPipeline {}
+
+impl Mesh {
+    pub fn new(gpu_device: *mut SDL_GPUDevice, vertices: &[Vertex3D], indices: &[u32]) -> Self {
+        unsafe {
+            let vertex_bytes = std::slice::from_raw_parts(
+                vertices.as_ptr() as *const u8,
+                vertices.len() * size_of::<Vertex3D>()
+            );
+            let index_bytes = std::slice::from_raw_parts(
+                indices.as_ptr() as *const u8,
+                indices.len() * size_of::<u32>()
+            );
+
+            Self {
+                vertex_buffer: upload_buffer(gpu_device, SDL_GPU_BUFFERUSAGE_VERTEX, vertex_bytes),
+                index_buffer: upload_buffer(gpu_device, SDL_GPU_BUFFERUSAGE_INDEX, index_bytes),
+                index_count: indices.len() as u32,
+            }
+        }
+    }
+
+    /// A unit cube centered on the origin with a full texture on every face.
+    pub fn cube(gpu_device: *mut SDL_GPUDevice) -> Self {
+        let faces = [
+            (Vec3::X, Vec3::Z),
+            (Vec3::NEG_X, Vec3::NEG_Z),
+            (Vec3::Y, Vec3::X),
+            (Vec3::NEG_Y, Vec3::X),
+            (Vec3::Z, Vec3::NEG_X),
+            (Vec3::NEG_Z, Vec3::X),
+        ];
+
+        let mut vertices = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+        for (normal, right) in faces {
+            let up = normal.cross(right);
+            let first = vertices.len() as u32;
+
+            for uv in [Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0)] {
+                vertices.push(Vertex3D {
+                    position: (normal + right * (uv.x * 2.0 - 1.0) + up * (1.0 - uv.y * 2.0)) * 0.5,
+                    normal,
+                    uv,
+                });
+            }
+            indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+
+        Self::new(gpu_device, &vertices, &indices)
+    }
+
+    pub fn release(&self, gpu_device: *mut SDL_GPUDevice) {
+        unsafe {
+            SDL_ReleaseGPUBuffer(gpu_device, self.vertex_buffer);
+            SDL_ReleaseGPUBuffer(gpu_device, self.index_buffer);
+        }
+    }
+}
+
+impl Default for Transform3D {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl Transform3D {
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Default::default()
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for GlobalTransform3D {
+    fn default() -> Self {
+        Self(Mat4::IDENTITY)
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE,
+            texture: null_mut(),
+        }
+    }
+}
+
+impl Default for MeshLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(-0.4, -1.0, 0.6),
+            ambient: 0.25,
+        }
+    }
+}
+
+impl FlyCamera {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            yaw: 0.0,
+            pitch: 0.0,
+            fov_y: (60.0f32).to_radians(),
+            speed: 5.0,
+            sensitivity: 0.004,
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos()
+        )
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_to_lh(self.position, self.forward(), Vec3::Y)
+    }
+}
+
+fn create_white_texture(gpu_device: *mut SDL_GPUDevice) -> *mut SDL_GPUTexture {
+    unsafe {
+        let image = SDL_CreateSurface(1, 1, SDL_PIXELFORMAT_ABGR8888);
+        let pixel: [u8; 4] = [255, 255, 255, 255];
+        SDL_memcpy((*image).pixels, pixel.as_ptr() as *const c_void, 4);
+
+        let texture = upload_texture(gpu_device, image);
+        SDL_DestroySurface(image);
+        texture
+    }
+}
+
+impl Module for MeshModule {
+    fn module(world: &World) {
+        world.component::<Mesh>();
+        world.component::<Transform3D>();
+        world.component::<GlobalTransform3D>();
+        world.component::<Material>();
+        world.component::<MeshLight>();
+        world.component::<FlyCamera>();
+        world.component::<MeshPipeline>();
+
+        world.set(MeshLight::default());
+
+        let meshes_query = world
+            .query::<
+                (
+                    &Mesh,
+                    &Transform3D,
+                    Option<&GlobalTransform3D>,
+                    Option<&Material>,
+                    Option<&RenderLayers>,
+                )
+            >()
+            .set_cached()
+            .build();
+
+        observer!("init_mesh_shaders", world, ShadersInitEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+            let gpu_device = event.gpu_device;
+
+            // Meshes are skipped while there is no `MeshPipeline`
+            let vertex_shader = match load_shader(gpu_device, "mesh.vert", 0, 1, 0, 0) {
+                Ok(shader) => shader,
+                Err(error) => {
+                    println!("Meshes disabled: {}", error);
+                    return;
+                }
+            };
+            let fragment_shader = match load_shader(gpu_device, "mesh.frag", 1, 1, 0, 0) {
+                Ok(shader) => shader,
+                Err(error) => {
+                    unsafe {
+                        SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                    }
+                    println!("Meshes disabled: {}", error);
+                    return;
+                }
+            };
+
+            unsafe {
+                let vertex_attributes = [
+                    SDL_GPUVertexAttribute {
+                        location: 0,
+                        buffer_slot: 0,
+                        format: SDL_GPU_VERTEXELEMENTFORMAT_FLOAT3,
+                        offset: 0,
+                    },
+                    SDL_GPUVertexAttribute {
+                        location: 1,
+                        buffer_slot: 0,
+                        format: SDL_GPU_VERTEXELEMENTFORMAT_FLOAT3,
+                        offset: size_of::<Vec3>() as u32,
+                    },
+                    SDL_GPUVertexAttribute {
+                        location: 2,
+                        buffer_slot: 0,
+                        format: SDL_GPU_VERTEXELEMENTFORMAT_FLOAT2,
+                        offset: (size_of::<Vec3>() * 2) as u32,
+                    },
+                ];
+
+                let pipeline_create_info = SDL_GPUGraphicsPipelineCreateInfo {
+                    target_info: SDL_GPUGraphicsPipelineTargetInfo {
+                        num_color_targets: 1,
+                        color_target_descriptions: &(SDL_GPUColorTargetDescription {
+                            format: SDL_GetGPUSwapchainTextureFormat(gpu_device, event.window),
+                            ..Default::default()
+                        }),
+                        depth_stencil_format: DEPTH_FORMAT,
+                        has_depth_stencil_target: true,
+                        ..Default::default()
+                    },
+                    vertex_input_state: SDL_GPUVertexInputState {
+                        vertex_buffer_descriptions: &(SDL_GPUVertexBufferDescription {
+                            slot: 0,
+                            pitch: size_of::<Vertex3D>() as u32,
+                            input_rate: SDL_GPU_VERTEXINPUTRATE_VERTEX,
+                            instance_step_rate: 0,
+                        }),
+                        num_vertex_buffers: 1,
+                        vertex_attributes: vertex_attributes.as_ptr(),
+                        num_vertex_attributes: vertex_attributes.len() as u32,
+                    },
+                    // Imported models mix windings, so nothing is culled
+                    rasterizer_state: SDL_GPURasterizerState {
+                        cull_mode: SDL_GPU_CULLMODE_NONE,
+                        ..Default::default()
+                    },
+                    depth_stencil_state: SDL_GPUDepthStencilState {
+                        compare_op: SDL_GPU_COMPAREOP_LESS,
+                        enable_depth_test: true,
+                        enable_depth_write: true,
+                        ..Default::default()
+                    },
+                    multisample_state: SDL_GPUMultisampleState {
+                        sample_count: event.sample_count,
+                        ..Default::default()
+                    },
+                    primitive_type: SDL_GPU_PRIMITIVETYPE_TRIANGLELIST,
+                    vertex_shader,
+                    fragment_shader,
+                    ..Default::default()
+                };
+
+                let pipeline = SDL_CreateGPUGraphicsPipeline(gpu_device, &pipeline_create_info);
+                if pipeline == null_mut() {
+                    panic!("Failed to create Mesh pipeline");
+                }
+
+                SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                SDL_ReleaseGPUShader(gpu_device, fragment_shader);
+
+                let sampler = SDL_CreateGPUSampler(
+                    gpu_device,
+                    &(SDL_GPUSamplerCreateInfo {
+                        min_filter: SDL_GPU_FILTER_LINEAR,
+                        mag_filter: SDL_GPU_FILTER_LINEAR,
+                        mipmap_mode: SDL_GPU_SAMPLERMIPMAPMODE_LINEAR,
+                        address_mode_u: SDL_GPU_SAMPLERADDRESSMODE_REPEAT,
+                        address_mode_v: SDL_GPU_SAMPLERADDRESSMODE_REPEAT,
+                        address_mode_w: SDL_GPU_SAMPLERADDRESSMODE_REPEAT,
+                        ..Default::default()
+                    })
+                );
+
+                world.set(MeshPipeline {
+                    pipeline,
+                    sampler,
+                    white_texture: create_white_texture(gpu_device),
+                });
+            }
+        });
+
+        // Parents are visited before their children, so theirs is already up to date
+        world
+            .system_named::<(&Transform3D, Option<&GlobalTransform3D>, &mut GlobalTransform3D)>(
+                "propagate_transform3d"
+            )
+            .term_at(1)
+            .parent()
+            .cascade()
+            .kind::<PostUpdate>()
+            .each(|(transform, parent, global)| {
+                global.0 = parent.map_or(Mat4::IDENTITY, |parent| parent.0) * transform.matrix();
+            });
+
+        system!(
+            "fly_camera",
+            world,
+            &Time($),
+            &InputMap($),
+            &Mouse($),
+            &mut Camera,
+            &CameraView,
+            &mut FlyCamera
+        )
+            .kind::<PostUpdate>()
+            .each(|(time, input, mouse, camera, view, fly)| {
+                if mouse.pressed(SDL_BUTTON_RIGHT as u8) {
+                    fly.yaw += mouse.delta.x * fly.sensitivity;
+                    fly.pitch = (fly.pitch - mouse.delta.y * fly.sensitivity).clamp(-1.5, 1.5);
+                }
+
+                let forward = fly.forward();
+                let right = Vec3::Y.cross(forward).normalize_or_zero();
+                let movement = input.axis2d("fly");
+                fly.position +=
+                    (right * movement.x +
+                        forward * movement.y +
+                        Vec3::Y * input.value("fly_vertical")) *
+                    fly.speed *
+                    time.unscaled_delta;
+
+                let aspect = if view.viewport.h > 0.0 { view.viewport.w / view.viewport.h } else { 1.0 };
+                camera.projection = Mat4::perspective_lh(fly.fov_y, aspect, camera.near, camera.far);
+                camera.view = fly.view();
+                camera.view_projection = camera.projection * camera.view;
+            });
+
+        observer!("mesh_render", world, RenderEvent, flecs::Any).each_iter(move |it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+
+            world.get::<(Option<&MeshPipeline>, &MeshLight)>(|(pipeline, light)| unsafe {
+                let Some(pipeline) = pipeline else {
+                    return;
+                };
+                let render_pass = event.render_pass;
+                let mut bound = false;
+
+                meshes_query.each(|(mesh, transform, global, material, render_layers)| {
+                    if (render_layers.copied().unwrap_or_default().0 & event.layer_mask) == 0 {
+                        return;
+                    }
+
+                    if !bound {
+                        SDL_BindGPUGraphicsPipeline(render_pass, pipeline.pipeline);
+                        bound = true;
+                    }
+
+                    let material = material.copied().unwrap_or_default();
+                    let texture = if material.texture == null_mut() {
+                        pipeline.white_texture
+                    } else {
+                        material.texture
+                    };
+
+                    let vertex_uniforms = MeshVertexUniforms {
+                        view_projection: event.view_projection,
+                        model: global.map_or_else(|| transform.matrix(), |global| global.0),
+                    };
+                    let fragment_uniforms = MeshFragmentUniforms {
+                        base_color: material.base_color,
+                        light: light.direction.normalize_or_zero().extend(light.ambient),
+                    };
+
+                    SDL_PushGPUVertexUniformData(
+                        event.command_buffer,
+                        0,
+                        &vertex_uniforms as *const _ as *const c_void,
+                        size_of::<MeshVertexUniforms>() as u32
+                    );
+                    SDL_PushGPUFragmentUniformData(
+                        event.command_buffer,
+                        0,
+                        &fragment_uniforms as *const _ as *const c_void,
+                        size_of::<MeshFragmentUniforms>() as u32
+                    );
+                    SDL_BindGPUFragmentSamplers(
+                        render_pass,
+                        0,
+                        &(SDL_GPUTextureSamplerBinding {
+                            texture,
+                            sampler: pipeline.sampler,
+                        }),
+                        1
+                    );
+                    SDL_BindGPUVertexBuffers(
+                        render_pass,
+                        0,
+                        &(SDL_GPUBufferBinding {
+                            buffer: mesh.vertex_buffer,
+                            offset: 0,
+                        }),
+                        1
+                    );
+                    SDL_BindGPUIndexBuffer(
+                        render_pass,
+                        &(SDL_GPUBufferBinding {
+                            buffer: mesh.index_buffer,
+                            offset: 0,
+                        }),
+                        SDL_GPU_INDEXELEMENTSIZE_32BIT
+                    );
+                    SDL_DrawGPUIndexedPrimitives(render_pass, mesh.index_count, 1, 0, 0, 0);
+                });
+            });
+        });
+    }
+}
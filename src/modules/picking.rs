@@ -0,0 +1,161 @@
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PreUpdate }, Entity, World, WorldGet },
+    macros::{ system, Component },
+    prelude::{ Builder, Module, QueryAPI, QueryBuilderImpl, SystemAPI },
+};
+use glam::Vec2;
+
+use crate::{
+    camera::{ Camera, CameraView, RenderLayers },
+    modules::{ input::Mouse, sprites::{ Layer, Sprite } },
+    window::WindowSize,
+};
+
+/// Finds the sprite under the cursor every frame and emits `SpriteHoverEvent` and
+/// `SpriteClickEvent`. Needs `InputModule`, `ScalingModule` and `SpritesModule`.
+#[derive(Component)]
+pub struct PickingModule;
+
+/// Result of the last picking pass.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Picking {
+    /// Camera whose viewport is under the cursor.
+    pub camera: Option<Entity>,
+    /// Cursor position in that camera's world space.
+    pub world_position: Vec2,
+    /// Front-most sprite under the cursor.
+    pub hovered: Option<Entity>,
+}
+
+/// Emitted when the cursor enters (`hovered`) or leaves a sprite.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpriteHoverEvent {
+    pub entity: Entity,
+    pub hovered: bool,
+}
+
+/// Emitted when a mouse button is pressed over a sprite.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpriteClickEvent {
+    pub entity: Entity,
+    /// `SDL_BUTTON_LEFT`, `SDL_BUTTON_MIDDLE`, `SDL_BUTTON_RIGHT`, ...
+    pub button: u8,
+    pub world_position: Vec2,
+}
+
+const MOUSE_BUTTON_COUNT: u8 = 5;
+
+impl Module for PickingModule {
+    fn module(world: &World) {
+        world.component::<Picking>();
+        world.component::<SpriteHoverEvent>();
+        world.component::<SpriteClickEvent>();
+
+        world.set(Picking::default());
+
+        let sprites_query = world
+            .query::<(&Sprite, Option<&Layer>, Option<&RenderLayers>)>()
+            .set_cached()
+            .build();
+        let cameras_query = world.query::<(&Camera, &CameraView)>().set_cached().build();
+
+        // Picks against last frame's cameras and viewports, which is what is on screen.
+        system!("sprite_picking", world, &Mouse($), &WindowSize($), &mut Picking($))
+            .kind::<PreUpdate>()
+            .each_iter(move |it, _, (mouse, window_size, picking)| {
+                let world = it.world();
+
+                let pixel_density = if window_size.width > 0 {
+                    (window_size.pixel_width as f32) / (window_size.width as f32)
+                } else {
+                    1.0
+                };
+                let screen = mouse.position * pixel_density;
+
+                // The camera drawn last wins where viewports overlap
+                let mut top_camera: Option<(i32, Entity, Vec2, u32)> = None;
+                cameras_query.each_entity(|entity, (camera, view)| {
+                    if !view.contains(screen) {
+                        return;
+                    }
+                    if let Some((priority, id, _, _)) = top_camera {
+                        if (view.priority, *entity.id()) < (priority, *id) {
+                            return;
+                        }
+                    }
+
+                    let world_position = camera
+                        .screen_to_world(screen, view.viewport_rect())
+                        .truncate();
+                    top_camera = Some((view.priority, entity.id(), world_position, view.layer_mask));
+                });
+
+                picking.camera = top_camera.map(|(_, camera, _, _)| camera);
+                let layer_mask = match top_camera {
+                    Some((_, _, world_position, layer_mask)) => {
+                        picking.world_position = world_position;
+                        layer_mask
+                    }
+                    None => 0,
+                };
+
+                // Same front-to-back order the sprites are drawn in
+                let mut front: Option<(f32, Layer, Entity)> = None;
+                if layer_mask != 0 {
+                    sprites_query.each_entity(|entity, (sprite, layer, render_layers)| {
+                        let render_layers = render_layers.copied().unwrap_or_default();
+                        if (render_layers.0 & layer_mask) == 0 {
+                            return;
+                        }
+                        if !sprite.contains(picking.world_position) {
+                            return;
+                        }
+
+                        let key = (sprite.position.z, layer.copied().unwrap_or_default(), entity.id());
+                        let in_front = match front {
+                            Some((z, layer, id)) =>
+                                key.0
+                                    .total_cmp(&z)
+                                    .then(key.1.cmp(&layer))
+                                    .then((*key.2).cmp(&*id))
+                                    .is_gt(),
+                            None => true,
+                        };
+                        if in_front {
+                            front = Some(key);
+                        }
+                    });
+                }
+
+                let hovered = front.map(|(_, _, entity)| entity);
+                if hovered != picking.hovered {
+                    if let Some(entity) = picking.hovered {
+                        world.event().entity(flecs::Any).emit(&SpriteHoverEvent {
+                            entity,
+                            hovered: false,
+                        });
+                    }
+                    if let Some(entity) = hovered {
+                        world.event().entity(flecs::Any).emit(&SpriteHoverEvent {
+                            entity,
+                            hovered: true,
+                        });
+                    }
+                    picking.hovered = hovered;
+                }
+
+                let Some(entity) = hovered else {
+                    return;
+                };
+                for button in 1..=MOUSE_BUTTON_COUNT {
+                    if mouse.just_pressed(button) {
+                        world.event().entity(flecs::Any).emit(&SpriteClickEvent {
+                            entity,
+                            button,
+                            world_position: picking.world_position,
+                        });
+                    }
+                }
+            });
+    }
+}
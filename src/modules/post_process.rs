@@ -0,0 +1,534 @@
+use std::{ ffi::c_void, ptr::null_mut };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PreStore }, EntityView, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, SystemAPI },
+};
+use glam::{ Vec2, Vec4 };
+use sdl3_sys::gpu::*;
+
+use crate::{
+    camera::MainCamera,
+    gpu::{ upload_rgba, GpuApi, GpuFrame, PostRenderEvent, ShadersInitEvent },
+    load_shader,
+    BASE_PATH,
+};
+
+/// Fullscreen effects applied to the scene after sprites rendered. Each effect is a component
+/// added to the `MainCamera` entity and they always run in the order `Pixelate`, `Bloom`,
+/// `ColorGrading`, `Vignette`, `Crt`.
+///
+/// Effects cover the whole window after every camera rendered, so they also apply to other
+/// cameras' viewports drawn on top of the main one. Effect components on any other camera are
+/// ignored.
+#[derive(Component)]
+pub struct PostProcessModule;
+
+#[derive(Component, Clone, Copy)]
+pub struct Pixelate {
+    /// Size of a pixel block in window pixels.
+    pub pixel_size: f32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct Bloom {
+    pub threshold: f32,
+    pub intensity: f32,
+    /// Blur radius in window pixels.
+    pub radius: f32,
+}
+
+#[derive(Component)]
+pub struct ColorGrading {
+    /// A `size^3` LUT stored as `size` slices of `size x size` laid out horizontally.
+    pub lut: *mut SDL_GPUTexture,
+    pub lut_size: f32,
+    pub intensity: f32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct Vignette {
+    pub intensity: f32,
+    pub radius: f32,
+    pub softness: f32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct Crt {
+    pub curvature: f32,
+    pub scanline_intensity: f32,
+    pub scanline_count: f32,
+}
+
+/// Layout shared by every post-processing fragment shader uniform block.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PostProcessUniform {
+    params: Vec4,
+    texel_size: Vec2,
+    padding: Vec2,
+}
+
+#[derive(Component)]
+pub struct PostProcessPipelines {
+    pub pixelate: *mut SDL_GPUGraphicsPipeline,
+    pub bloom: *mut SDL_GPUGraphicsPipeline,
+    pub color_grading: *mut SDL_GPUGraphicsPipeline,
+    pub vignette: *mut SDL_GPUGraphicsPipeline,
+    pub crt: *mut SDL_GPUGraphicsPipeline,
+    pub sampler: *mut SDL_GPUSampler,
+    pub format: SDL_GPUTextureFormat,
+}
+
+/// Ping-pong color targets the scene and intermediate effects render into.
+#[derive(Component)]
+pub struct PostProcessTargets {
+    pub textures: [*mut SDL_GPUTexture; 2],
+    pub width: u32,
+    pub height: u32,
+}
+
+struct PostProcessPass {
+    pipeline: *mut SDL_GPUGraphicsPipeline,
+    params: Vec4,
+    lut: *mut SDL_GPUTexture,
+}
+
+unsafe impl Send for ColorGrading {}
+unsafe impl Sync for ColorGrading {}
+
+unsafe impl Send for PostProcessPipelines {}
+unsafe impl Sync for PostProcessPipelines {}
+
+unsafe impl Send for PostProcessTargets {}
+unsafe impl Sync for PostProcessTargets {}
+
+impl Default for Pixelate {
+    fn default() -> Self {
+        Self { pixel_size: 4.0 }
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            intensity: 1.0,
+            radius: 8.0,
+        }
+    }
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            radius: 0.75,
+            softness: 0.45,
+        }
+    }
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self {
+            curvature: 0.1,
+            scanline_intensity: 0.25,
+            scanline_count: 600.0,
+        }
+    }
+}
+
+impl ColorGrading {
+    /// Loads the LUT from `Images/<file_name>`, which must be `size * size` pixels wide and
+    /// `size` pixels high.
+    pub fn new(file_name: &str, gpu_device: *mut SDL_GPUDevice, intensity: f32) -> Result<Self, String> {
+        let path = format!("{}/Images/{}", BASE_PATH, file_name);
+        let image = image::open(&path)
+            .map_err(|error| format!("{}: {}", path, error))?
+            .to_rgba8();
+
+        let size = image.height();
+        if size == 0 || size.checked_mul(size) != Some(image.width()) {
+            return Err(
+                format!("{}: a LUT must be size * size wide and size high, got {}x{}", path, image.width(), size)
+            );
+        }
+
+        let lut = upload_rgba(gpu_device, image.width(), image.height(), image.as_raw());
+        if lut.is_null() {
+            return Err(format!("{}: failed to upload the LUT", path));
+        }
+
+        Ok(Self {
+            lut,
+            lut_size: size as f32,
+            intensity,
+        })
+    }
+}
+
+impl PostProcessTargets {
+    fn resize(
+        &mut self,
+        gpu_device: *mut SDL_GPUDevice,
+        format: SDL_GPUTextureFormat,
+        width: u32,
+        height: u32
+    ) {
+        if self.width == width && self.height == height && self.textures[0] != null_mut() {
+            return;
+        }
+
+        unsafe {
+            for texture in self.textures.iter_mut() {
+                if *texture != null_mut() {
+                    SDL_ReleaseGPUTexture(gpu_device, *texture);
+                }
+
+                *texture = SDL_CreateGPUTexture(
+                    gpu_device,
+                    &(SDL_GPUTextureCreateInfo {
+                        r#type: SDL_GPU_TEXTURETYPE_2D,
+                        format,
+                        width,
+                        height,
+                        layer_count_or_depth: 1,
+                        num_levels: 1,
+                        usage: SDL_GPU_TEXTUREUSAGE_SAMPLER | SDL_GPU_TEXTUREUSAGE_COLOR_TARGET,
+                        ..Default::default()
+                    })
+                );
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+    }
+}
+
+fn has_post_process(camera: EntityView) -> bool {
+    camera.has::<Pixelate>() ||
+        camera.has::<Bloom>() ||
+        camera.has::<ColorGrading>() ||
+        camera.has::<Vignette>() ||
+        camera.has::<Crt>()
+}
+
+fn create_post_process_pipeline(
+    gpu_device: *mut SDL_GPUDevice,
+    format: SDL_GPUTextureFormat,
+    vertex_shader: *mut SDL_GPUShader,
+    file_name: &str,
+    sampler_count: u32
+) -> Result<*mut SDL_GPUGraphicsPipeline, String> {
+    let fragment_shader = load_shader(gpu_device, file_name, sampler_count, 1, 0, 0)?;
+
+    unsafe {
+        let pipeline_create_info = SDL_GPUGraphicsPipelineCreateInfo {
+            target_info: SDL_GPUGraphicsPipelineTargetInfo {
+                num_color_targets: 1,
+                color_target_descriptions: &(SDL_GPUColorTargetDescription {
+                    format,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            primitive_type: SDL_GPU_PRIMITIVETYPE_TRIANGLELIST,
+            vertex_shader,
+            fragment_shader,
+            ..Default::default()
+        };
+
+        let pipeline = SDL_CreateGPUGraphicsPipeline(gpu_device, &pipeline_create_info);
+        SDL_ReleaseGPUShader(gpu_device, fragment_shader);
+        if pipeline == null_mut() {
+            return Err(format!("Failed to create {} pipeline", file_name));
+        }
+
+        Ok(pipeline)
+    }
+}
+
+fn create_post_process_pipelines(
+    gpu_device: *mut SDL_GPUDevice,
+    format: SDL_GPUTextureFormat,
+    sampler: *mut SDL_GPUSampler
+) -> Result<PostProcessPipelines, String> {
+    let vertex_shader = load_shader(gpu_device, "fullscreen.vert", 0, 0, 0, 0)?;
+    let create = |file_name: &str, sampler_count: u32| {
+        create_post_process_pipeline(gpu_device, format, vertex_shader, file_name, sampler_count)
+    };
+
+    let effects = [
+        ("pixelate.frag", 1),
+        ("bloom.frag", 1),
+        ("color_grading.frag", 2),
+        ("vignette.frag", 1),
+        ("crt.frag", 1),
+    ];
+    let mut pipelines = Vec::with_capacity(effects.len());
+    for (file_name, sampler_count) in effects {
+        match create(file_name, sampler_count) {
+            Ok(pipeline) => pipelines.push(pipeline),
+            Err(error) => {
+                unsafe {
+                    for pipeline in pipelines {
+                        SDL_ReleaseGPUGraphicsPipeline(gpu_device, pipeline);
+                    }
+                    SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                }
+                return Err(error);
+            }
+        }
+    }
+
+    unsafe {
+        SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+    }
+
+    Ok(PostProcessPipelines {
+        pixelate: pipelines[0],
+        bloom: pipelines[1],
+        color_grading: pipelines[2],
+        vignette: pipelines[3],
+        crt: pipelines[4],
+        sampler,
+        format,
+    })
+}
+
+impl Module for PostProcessModule {
+    fn module(world: &World) {
+        world.component::<Pixelate>();
+        world.component::<Bloom>();
+        world.component::<ColorGrading>();
+        world.component::<Vignette>();
+        world.component::<Crt>();
+        world.component::<PostProcessPipelines>();
+        world.component::<PostProcessTargets>();
+
+        world.set(PostProcessTargets {
+            textures: [null_mut(); 2],
+            width: 0,
+            height: 0,
+        });
+
+        observer!("init_post_process_shaders", world, ShadersInitEvent, flecs::Any).each_iter(
+            |it, _, _| {
+                let event = &*it.param();
+                let world = it.world();
+                let gpu_device = event.gpu_device;
+
+                unsafe {
+                    let format = SDL_GetGPUSwapchainTextureFormat(gpu_device, event.window);
+                    let sampler = SDL_CreateGPUSampler(
+                        gpu_device,
+                        &(SDL_GPUSamplerCreateInfo {
+                            min_filter: SDL_GPU_FILTER_LINEAR,
+                            mag_filter: SDL_GPU_FILTER_LINEAR,
+                            mipmap_mode: SDL_GPU_SAMPLERMIPMAPMODE_NEAREST,
+                            address_mode_u: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                            address_mode_v: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                            address_mode_w: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                            ..Default::default()
+                        })
+                    );
+
+                    // Without pipelines the scene keeps rendering straight to the swapchain
+                    match create_post_process_pipelines(gpu_device, format, sampler) {
+                        Ok(pipelines) => {
+                            world.set(pipelines);
+                            println!("Setting Post Process Pipelines");
+                        }
+                        Err(error) => {
+                            SDL_ReleaseGPUSampler(gpu_device, sampler);
+                            println!("Post processing disabled: {}", error);
+                        }
+                    }
+                }
+            }
+        );
+
+        system!("post_process_targets", world, &GpuApi($), &PostProcessPipelines($), &mut PostProcessTargets($), &mut GpuFrame($))
+            .kind::<PreStore>()
+            .each_iter(|it, _, (gpu_api, pipelines, targets, frame)| {
+                if frame.swapchain_texture == null_mut() {
+                    return;
+                }
+
+                let world = it.world();
+                let Some(camera) = MainCamera::entity(&world) else {
+                    return;
+                };
+                if !has_post_process(camera) {
+                    return;
+                }
+
+                targets.resize(gpu_api.gpu_device, pipelines.format, frame.width, frame.height);
+                frame.color_target = targets.textures[0];
+            });
+
+        observer!("post_process", world, PostRenderEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+            let camera = MainCamera::entity(&world);
+
+            world.get::<(&PostProcessPipelines, &PostProcessTargets)>(|(pipelines, targets)| {
+                let mut passes: Vec<PostProcessPass> = Vec::new();
+
+                if let Some(camera) = camera {
+                    camera.get::<
+                        (
+                            Option<&Pixelate>,
+                            Option<&Bloom>,
+                            Option<&ColorGrading>,
+                            Option<&Vignette>,
+                            Option<&Crt>,
+                        )
+                    >(|(pixelate, bloom, color_grading, vignette, crt)| {
+                        if let Some(pixelate) = pixelate {
+                            passes.push(PostProcessPass {
+                                pipeline: pipelines.pixelate,
+                                params: Vec4::new(pixelate.pixel_size, 0.0, 0.0, 0.0),
+                                lut: null_mut(),
+                            });
+                        }
+                        if let Some(bloom) = bloom {
+                            passes.push(PostProcessPass {
+                                pipeline: pipelines.bloom,
+                                params: Vec4::new(bloom.threshold, bloom.intensity, bloom.radius, 0.0),
+                                lut: null_mut(),
+                            });
+                        }
+                        if let Some(color_grading) = color_grading {
+                            passes.push(PostProcessPass {
+                                pipeline: pipelines.color_grading,
+                                params: Vec4::new(
+                                    color_grading.intensity,
+                                    color_grading.lut_size,
+                                    0.0,
+                                    0.0
+                                ),
+                                lut: color_grading.lut,
+                            });
+                        }
+                        if let Some(vignette) = vignette {
+                            passes.push(PostProcessPass {
+                                pipeline: pipelines.vignette,
+                                params: Vec4::new(
+                                    vignette.intensity,
+                                    vignette.radius,
+                                    vignette.softness,
+                                    0.0
+                                ),
+                                lut: null_mut(),
+                            });
+                        }
+                        if let Some(crt) = crt {
+                            passes.push(PostProcessPass {
+                                pipeline: pipelines.crt,
+                                params: Vec4::new(
+                                    crt.curvature,
+                                    crt.scanline_intensity,
+                                    crt.scanline_count,
+                                    0.0
+                                ),
+                                lut: null_mut(),
+                            });
+                        }
+                    });
+                }
+
+                unsafe {
+                    // Effects were removed after the scene was redirected, copy it through as-is.
+                    if passes.is_empty() {
+                        SDL_BlitGPUTexture(
+                            event.command_buffer,
+                            &(SDL_GPUBlitInfo {
+                                source: SDL_GPUBlitRegion {
+                                    texture: event.scene_texture,
+                                    w: event.width,
+                                    h: event.height,
+                                    ..Default::default()
+                                },
+                                destination: SDL_GPUBlitRegion {
+                                    texture: event.swapchain_texture,
+                                    w: event.width,
+                                    h: event.height,
+                                    ..Default::default()
+                                },
+                                load_op: SDL_GPU_LOADOP_DONT_CARE,
+                                filter: SDL_GPU_FILTER_NEAREST,
+                                ..Default::default()
+                            })
+                        );
+                        return;
+                    }
+
+                    let texel_size = Vec2::new(1.0 / (event.width as f32), 1.0 / (event.height as f32));
+                    let mut source = event.scene_texture;
+
+                    for (i, pass) in passes.iter().enumerate() {
+                        let target = if i == passes.len() - 1 {
+                            event.swapchain_texture
+                        } else if source == targets.textures[0] {
+                            targets.textures[1]
+                        } else {
+                            targets.textures[0]
+                        };
+
+                        let mut color_target_info = SDL_GPUColorTargetInfo::default();
+                        color_target_info.texture = target;
+                        color_target_info.load_op = SDL_GPU_LOADOP_DONT_CARE;
+                        color_target_info.store_op = SDL_GPU_STOREOP_STORE;
+
+                        let render_pass = SDL_BeginGPURenderPass(
+                            event.command_buffer,
+                            &color_target_info,
+                            1,
+                            null_mut()
+                        );
+
+                        SDL_BindGPUGraphicsPipeline(render_pass, pass.pipeline);
+
+                        let mut bindings = vec![SDL_GPUTextureSamplerBinding {
+                            texture: source,
+                            sampler: pipelines.sampler,
+                        }];
+                        if pass.lut != null_mut() {
+                            bindings.push(SDL_GPUTextureSamplerBinding {
+                                texture: pass.lut,
+                                sampler: pipelines.sampler,
+                            });
+                        }
+                        SDL_BindGPUFragmentSamplers(
+                            render_pass,
+                            0,
+                            bindings.as_ptr(),
+                            bindings.len() as u32
+                        );
+
+                        let uniform = PostProcessUniform {
+                            params: pass.params,
+                            texel_size,
+                            padding: Vec2::ZERO,
+                        };
+                        SDL_PushGPUFragmentUniformData(
+                            event.command_buffer,
+                            0,
+                            &uniform as *const _ as *const c_void,
+                            size_of::<PostProcessUniform>() as u32
+                        );
+
+                        SDL_DrawGPUPrimitives(render_pass, 3, 1, 0, 0);
+                        SDL_EndGPURenderPass(render_pass);
+
+                        source = target;
+                    }
+                }
+            });
+        });
+    }
+}
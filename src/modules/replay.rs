@@ -0,0 +1,272 @@
+use std::{
+    fs::File,
+    io::{ BufReader, BufWriter, Read, Write },
+};
+
+use flecs_ecs::{
+    core::{ flecs::pipeline::{ OnLoad, PostLoad }, World },
+    macros::{ system, Component },
+    prelude::{ Module, SystemAPI },
+};
+use glam::Vec2;
+use sdl3_sys::scancode::SDL_SCANCODE_COUNT;
+
+use crate::modules::input::{ InputState, JOYSTICK_AXES };
+
+/// Records `InputState` and the frame time every frame, or replays a recording in their place.
+/// Import after `InputModule` and `GamepadModule` so the replay can overwrite their sampled
+/// state before actions are evaluated at `PostLoad`. Recording happens at `PostLoad`, so it
+/// stores the state the actions saw, e.g. with the keys `TextInputModule` released while typing.
+///
+/// Only input is recorded, a replay reproduces a session when it starts from the same `Random`
+/// seed and drives `run_frame` with `Replay::next_delta_time`.
+#[derive(Component)]
+pub struct ReplayModule;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    Off,
+    Recording,
+    Replaying,
+}
+
+#[derive(Clone, Default)]
+pub struct ReplayFrame {
+    pub delta_time: f32,
+    pub state: InputState,
+}
+
+#[derive(Component)]
+pub struct Replay {
+    pub mode: ReplayMode,
+    /// Seed for `Random`, stored in the recording.
+    pub seed: u64,
+    pub frames: Vec<ReplayFrame>,
+    /// Next frame to replay.
+    pub cursor: usize,
+}
+
+const MAGIC: &[u8; 8] = b"SDLFREC2";
+
+impl Replay {
+    pub fn off() -> Self {
+        Self {
+            mode: ReplayMode::Off,
+            seed: 0,
+            frames: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    pub fn record(seed: u64) -> Self {
+        Self {
+            mode: ReplayMode::Recording,
+            seed,
+            ..Self::off()
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|error| format!("{}: {}", path, error))?;
+        let mut reader = BufReader::new(file);
+        let error = |error: std::io::Error| format!("{}: {}", path, error);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).map_err(error)?;
+        if &magic != MAGIC {
+            return Err(format!("{}: not an input recording", path));
+        }
+
+        let seed = read_u64(&mut reader).map_err(error)?;
+        let frame_count = read_u32(&mut reader).map_err(error)?;
+
+        // Not preallocated from `frame_count`, a corrupt count would abort on allocation before
+        // the truncated data is noticed
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            frames.push(read_frame(&mut reader).map_err(error)?);
+        }
+
+        Ok(Self {
+            mode: ReplayMode::Replaying,
+            seed,
+            frames,
+            cursor: 0,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|error| format!("{}: {}", path, error))?;
+        let mut writer = BufWriter::new(file);
+
+        let result = (|| -> std::io::Result<()> {
+            writer.write_all(MAGIC)?;
+            writer.write_all(&self.seed.to_le_bytes())?;
+            writer.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+            for frame in &self.frames {
+                write_frame(&mut writer, frame)?;
+            }
+            writer.flush()
+        })();
+
+        result.map_err(|error| format!("{}: {}", path, error))
+    }
+
+    /// The frame time to pass to `run_frame` while replaying.
+    pub fn next_delta_time(&self) -> Option<f32> {
+        if self.mode != ReplayMode::Replaying {
+            return None;
+        }
+
+        self.frames.get(self.cursor).map(|frame| frame.delta_time)
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> std::io::Result<f32> {
+    Ok(f32::from_bits(read_u32(reader)?))
+}
+
+fn read_vec2(reader: &mut impl Read) -> std::io::Result<Vec2> {
+    Ok(Vec2::new(read_f32(reader)?, read_f32(reader)?))
+}
+
+fn write_vec2(writer: &mut impl Write, value: Vec2) -> std::io::Result<()> {
+    writer.write_all(&value.x.to_le_bytes())?;
+    writer.write_all(&value.y.to_le_bytes())
+}
+
+/// Only held scancodes are stored, most frames have none or a few.
+fn write_frame(writer: &mut impl Write, frame: &ReplayFrame) -> std::io::Result<()> {
+    let state = &frame.state;
+
+    writer.write_all(&frame.delta_time.to_le_bytes())?;
+
+    writer.write_all(&(state.keys.len() as u32).to_le_bytes())?;
+    let held: Vec<u32> = state.keys
+        .iter()
+        .enumerate()
+        .filter(|(_, held)| **held)
+        .map(|(scancode, _)| scancode as u32)
+        .collect();
+    writer.write_all(&(held.len() as u32).to_le_bytes())?;
+    for scancode in held {
+        writer.write_all(&scancode.to_le_bytes())?;
+    }
+
+    writer.write_all(&state.mouse_buttons.to_le_bytes())?;
+    write_vec2(writer, state.mouse_position)?;
+    write_vec2(writer, state.mouse_delta)?;
+    write_vec2(writer, state.mouse_wheel)?;
+
+    writer.write_all(&state.gamepad_buttons.to_le_bytes())?;
+    for axis in state.gamepad_axes {
+        writer.write_all(&axis.to_le_bytes())?;
+    }
+
+    writer.write_all(&state.joystick_buttons.to_le_bytes())?;
+    for axis in state.joystick_axes {
+        writer.write_all(&axis.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn read_frame(reader: &mut impl Read) -> std::io::Result<ReplayFrame> {
+    let delta_time = read_f32(reader)?;
+
+    let key_count = read_u32(reader)? as usize;
+    if key_count > SDL_SCANCODE_COUNT.0 as usize {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid key count"));
+    }
+    let mut keys = vec![false; key_count];
+    let held_count = read_u32(reader)?;
+    for _ in 0..held_count {
+        let scancode = read_u32(reader)? as usize;
+        if let Some(key) = keys.get_mut(scancode) {
+            *key = true;
+        }
+    }
+
+    let mouse_buttons = read_u32(reader)?;
+    let mouse_position = read_vec2(reader)?;
+    let mouse_delta = read_vec2(reader)?;
+    let mouse_wheel = read_vec2(reader)?;
+
+    let gamepad_buttons = read_u64(reader)?;
+    let mut gamepad_axes = [0.0; 6];
+    for axis in gamepad_axes.iter_mut() {
+        *axis = read_f32(reader)?;
+    }
+
+    let joystick_buttons = read_u64(reader)?;
+    let mut joystick_axes = [0.0; JOYSTICK_AXES];
+    for axis in joystick_axes.iter_mut() {
+        *axis = read_f32(reader)?;
+    }
+
+    Ok(ReplayFrame {
+        delta_time,
+        state: InputState {
+            keys,
+            mouse_buttons,
+            mouse_position,
+            mouse_delta,
+            mouse_wheel,
+            gamepad_buttons,
+            gamepad_axes,
+            joystick_buttons,
+            joystick_axes,
+        },
+    })
+}
+
+impl Module for ReplayModule {
+    fn module(world: &World) {
+        world.component::<Replay>();
+
+        world.set(Replay::off());
+
+        system!("replay_input", world, &mut Replay($), &mut InputState($))
+            .kind::<OnLoad>()
+            .each(|(replay, state)| {
+                if replay.mode != ReplayMode::Replaying {
+                    return;
+                }
+
+                let Some(frame) = replay.frames.get(replay.cursor) else {
+                    println!("Replay finished after {} frames", replay.cursor);
+                    replay.mode = ReplayMode::Off;
+                    return;
+                };
+
+                *state = frame.state.clone();
+                replay.cursor += 1;
+            });
+
+        // After every `OnLoad` system changed the state, before it is cleared at `PostFrame`
+        system!("record_input", world, &mut Replay($), &InputState($))
+            .kind::<PostLoad>()
+            .each_iter(|it, _, (replay, state)| {
+                if replay.mode != ReplayMode::Recording {
+                    return;
+                }
+
+                replay.frames.push(ReplayFrame {
+                    delta_time: it.delta_time(),
+                    state: state.clone(),
+                });
+            });
+    }
+}
@@ -0,0 +1,152 @@
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PostUpdate }, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, SystemAPI },
+};
+use glam::{ Vec2, Vec4 };
+use sdl3_sys::gpu::SDL_GPUViewport;
+
+use crate::{
+    camera::{ Camera, CameraView },
+    modules::camera2d::Camera2D,
+    window::{ Window, WindowResizedEvent, WindowSize },
+};
+
+/// Keeps `WindowSize` in sync with the window and sizes every camera's `CameraView::viewport`
+/// from its window rect. Cameras with a `Scaling` component also get their projection (or their
+/// `Camera2D` size) and viewport fitted to that rect every frame.
+#[derive(Component)]
+pub struct ScalingModule;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// The virtual resolution fills the window, ignoring the aspect ratio.
+    Stretch,
+    /// The virtual resolution is scaled to fit, with bars on the sides that do not fit.
+    Letterbox,
+    /// Like `Letterbox` but only scales by whole multiples for pixel-perfect art.
+    IntegerScale,
+    /// The virtual resolution is always visible and the extra window area shows more world.
+    Expand,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Scaling {
+    pub mode: ScalingMode,
+    pub virtual_width: f32,
+    pub virtual_height: f32,
+}
+
+impl Scaling {
+    pub fn new(mode: ScalingMode, virtual_width: f32, virtual_height: f32) -> Self {
+        Self {
+            mode,
+            virtual_width,
+            virtual_height,
+        }
+    }
+
+    /// Returns the visible world size and the viewport in pixels for a window of the given
+    /// pixel size.
+    pub fn apply(&self, pixel_width: f32, pixel_height: f32) -> (Vec2, SDL_GPUViewport) {
+        let virtual_size = Vec2::new(self.virtual_width, self.virtual_height);
+        let fit = (pixel_width / self.virtual_width).min(pixel_height / self.virtual_height);
+
+        let full = SDL_GPUViewport {
+            x: 0.0,
+            y: 0.0,
+            w: pixel_width,
+            h: pixel_height,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+
+        let centered = |scale: f32| {
+            let w = self.virtual_width * scale;
+            let h = self.virtual_height * scale;
+            SDL_GPUViewport {
+                x: ((pixel_width - w) / 2.0).floor(),
+                y: ((pixel_height - h) / 2.0).floor(),
+                w,
+                h,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }
+        };
+
+        match self.mode {
+            ScalingMode::Stretch => (virtual_size, full),
+            ScalingMode::Letterbox => (virtual_size, centered(fit)),
+            ScalingMode::IntegerScale => (virtual_size, centered(fit.floor().max(1.0))),
+            ScalingMode::Expand => (Vec2::new(pixel_width, pixel_height) / fit, full),
+        }
+    }
+}
+
+impl Module for ScalingModule {
+    fn module(world: &World) {
+        world.component::<WindowSize>();
+        world.component::<WindowResizedEvent>();
+        world.component::<Scaling>();
+
+        world.get::<&Window>(|window| {
+            world.set(window.size());
+        });
+
+        observer!("window_resized", world, WindowResizedEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            it.world().set(event.size);
+        });
+
+        system!(
+            "apply_scaling",
+            world,
+            &WindowSize($),
+            &mut Camera,
+            &mut CameraView,
+            ?&Scaling,
+            ?&mut Camera2D
+        )
+            .kind::<PostUpdate>()
+            .each(|(window_size, camera, view, scaling, camera2d)| {
+                let window = Vec4::new(
+                    window_size.pixel_width as f32,
+                    window_size.pixel_height as f32,
+                    window_size.pixel_width as f32,
+                    window_size.pixel_height as f32
+                );
+                let area = view.rect * window;
+
+                let Some(scaling) = scaling else {
+                    view.viewport = SDL_GPUViewport {
+                        x: area.x,
+                        y: area.y,
+                        w: area.z,
+                        h: area.w,
+                        min_depth: 0.0,
+                        max_depth: 1.0,
+                    };
+                    if let Some(camera2d) = camera2d {
+                        camera2d.viewport = Some(area);
+                    }
+                    return;
+                };
+
+                let (visible, mut viewport) = scaling.apply(area.z, area.w);
+                viewport.x += area.x;
+                viewport.y += area.y;
+
+                // A `Camera2D` builds the projection itself from the visible size
+                match camera2d {
+                    Some(camera2d) => {
+                        camera2d.size = visible;
+                        camera2d.viewport = Some(
+                            Vec4::new(viewport.x, viewport.y, viewport.w, viewport.h)
+                        );
+                    }
+                    None => camera.set_projection(0.0, visible.x, visible.y, 0.0),
+                }
+                view.viewport = viewport;
+            });
+    }
+}
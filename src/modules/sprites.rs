@@ -1,25 +1,18 @@
-use std::{ ffi::{ c_void, CStr }, ptr::null_mut };
+use std::{ collections::HashMap, ffi::c_void, ptr::null_mut };
 
 use flecs_ecs::{
-    core::{ flecs::{ self, pipeline::{ PreStore, PreUpdate } }, TermBuilderImpl, WorldGet },
+    core::{ flecs::{ self, pipeline::PreStore }, Entity, TermBuilderImpl, WorldGet },
     macros::{ observer, system, Component },
     prelude::{ Builder, Module, QueryAPI, QueryBuilderImpl, SystemAPI },
 };
 use glam::{ Mat4, Vec2, Vec3 };
-use sdl3_sys::{
-    error::SDL_GetError,
-    gpu::*,
-    pixels::SDL_FColor,
-    stdinc::SDL_memcpy,
-    surface::SDL_DestroySurface,
-};
+use sdl3_sys::{ gpu::*, surface::SDL_DestroySurface };
 
 use crate::{
-    camera::Camera,
-    gpu::{ GpuApi, ShadersInitEvent },
+    camera::{ Camera, CameraView, RenderLayers },
+    gpu::{ upload_texture, GpuApi, GpuFrame, RenderEvent, ShadersInitEvent, DEPTH_FORMAT },
     load_image,
     load_shader,
-    window::Window,
 };
 
 #[derive(Component)]
@@ -28,10 +21,13 @@ pub struct SpritesModule;
 #[repr(C)]
 #[derive(Component, Clone, Copy)]
 pub struct Sprite {
+    /// Sprites with a larger `z` are drawn in front, `z` must stay within the camera depth range.
     pub position: Vec3,
     pub rotation: f32,
     pub scale: Vec2,
-    pub padding: Vec2,
+    /// Non-zero when the sprite samples `SpritesBuffer::normal_map` for lighting.
+    pub normal_map: f32,
+    pub padding: f32,
     pub texture: Texture,
     pub color: ColorRgba,
 }
@@ -46,7 +42,7 @@ pub struct ColorRgba {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct Texture {
     pub u: f32,
     pub v: f32,
@@ -54,18 +50,88 @@ pub struct Texture {
     pub h: f32,
 }
 
+/// How a sprite is blended with what is already drawn. Sprites without a `BlendMode`
+/// component use `BlendMode::Alpha`.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Opaque,
+    #[default]
+    Alpha,
+    /// For textures whose color is already multiplied by alpha.
+    Premultiplied,
+    Additive,
+    Multiply,
+}
+
+/// Draw order between transparent sprites at the same `z`, higher layers are drawn on top.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Layer(pub i32);
+
+/// A run of consecutive sprites in `SpritesBuffer::data_buffer` sharing a blend mode.
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteBatch {
+    pub blend_mode: BlendMode,
+    pub first: usize,
+    pub count: usize,
+}
+
+/// The sprites one camera draws, packed at `first..first + count` in
+/// `SpritesBuffer::data_buffer` in draw order. Sprites seen by several cameras are packed once
+/// per camera.
+#[derive(Clone, Debug)]
+pub struct CameraSprites {
+    pub camera: Entity,
+    pub first: usize,
+    pub count: usize,
+    pub batches: Vec<SpriteBatch>,
+}
+
+/// How sprites are culled against the cameras before they are uploaded.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpriteCulling {
+    /// When false every sprite on a camera's render layers is drawn by it.
+    pub enabled: bool,
+    /// Buckets sprites into a grid of this cell size in world units so each camera only tests
+    /// the sprites in the cells it overlaps. Pays off with many cameras or large worlds.
+    pub grid_cell_size: Option<f32>,
+}
+
+/// Sprite counts of the last uploaded frame. A sprite is counted once per camera whose render
+/// layers it is on.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SpriteStats {
+    pub sprites: usize,
+    pub drawn: usize,
+    pub culled: usize,
+}
+
 #[derive(Component)]
 pub struct SpritesBuffer {
     pub data_buffer: *mut SDL_GPUBuffer,
     pub transfer_buffer: *mut SDL_GPUTransferBuffer,
     pub texture: *mut SDL_GPUTexture,
+    /// Optional normal map sharing the layout of `texture`, null when not loaded.
+    pub normal_map: *mut SDL_GPUTexture,
     pub sampler: *mut SDL_GPUSampler,
     pub count: usize,
     pub size: usize,
+    /// Number of sprites uploaded to `data_buffer` for the current frame, over all cameras.
+    pub draw_count: usize,
+    /// What each camera draws this frame.
+    pub cameras: Vec<CameraSprites>,
 }
 
+/// Sprite pipeline cache, one graphics pipeline is created per blend mode on first use.
+/// `BlendMode::Opaque` writes depth, the other modes only test against it.
 #[derive(Component)]
-pub struct TexturePipeline(pub *mut SDL_GPUGraphicsPipeline);
+pub struct TexturePipeline {
+    pub gpu_device: *mut SDL_GPUDevice,
+    pub format: SDL_GPUTextureFormat,
+    pub sample_count: SDL_GPUSampleCount,
+    pub vertex_shader: *mut SDL_GPUShader,
+    pub fragment_shader: *mut SDL_GPUShader,
+    pub pipelines: HashMap<BlendMode, *mut SDL_GPUGraphicsPipeline>,
+}
 
 unsafe impl Send for TexturePipeline {}
 unsafe impl Sync for TexturePipeline {}
@@ -81,19 +147,8 @@ impl SpritesBuffer {
         let image = load_image(file_name, 4);
 
         unsafe {
-            let texture = SDL_CreateGPUTexture(
-                gpu_device,
-                &(SDL_GPUTextureCreateInfo {
-                    r#type: SDL_GPU_TEXTURETYPE_2D,
-                    format: SDL_GPU_TEXTUREFORMAT_R8G8B8A8_UNORM,
-                    width: (*image).w as u32,
-                    height: (*image).h as u32,
-                    layer_count_or_depth: 1,
-                    num_levels: 1,
-                    usage: SDL_GPU_TEXTUREUSAGE_SAMPLER,
-                    ..Default::default()
-                })
-            );
+            let texture = upload_texture(gpu_device, image);
+            SDL_DestroySurface(image);
 
             let sampler = SDL_CreateGPUSampler(
                 gpu_device,
@@ -110,27 +165,6 @@ impl SpritesBuffer {
                 })
             );
 
-            let texture_transfer_buffer = SDL_CreateGPUTransferBuffer(
-                gpu_device,
-                &(SDL_GPUTransferBufferCreateInfo {
-                    usage: SDL_GPU_TRANSFERBUFFERUSAGE_UPLOAD,
-                    size: ((*image).w * (*image).h * 4) as u32,
-                    ..Default::default()
-                })
-            );
-
-            let texture_transfer_ptr = SDL_MapGPUTransferBuffer(
-                gpu_device,
-                texture_transfer_buffer,
-                false
-            );
-            SDL_memcpy(
-                texture_transfer_ptr,
-                (*image).pixels,
-                ((*image).w * (*image).h * 4) as usize
-            );
-            SDL_UnmapGPUTransferBuffer(gpu_device, texture_transfer_buffer);
-
             let transfer_buffer = SDL_CreateGPUTransferBuffer(
                 gpu_device,
                 &(SDL_GPUTransferBufferCreateInfo {
@@ -149,64 +183,185 @@ impl SpritesBuffer {
                 })
             );
 
-            let command_buffer = SDL_AcquireGPUCommandBuffer(gpu_device);
-            let copy_pass = SDL_BeginGPUCopyPass(command_buffer);
-
-            SDL_UploadToGPUTexture(
-                copy_pass,
-                &(SDL_GPUTextureTransferInfo {
-                    transfer_buffer: texture_transfer_buffer,
-                    offset: 0,
-                    ..Default::default()
-                }),
-                &(SDL_GPUTextureRegion {
-                    texture,
-                    w: (*image).w as u32,
-                    h: (*image).h as u32,
-                    d: 1,
-                    ..Default::default()
-                }),
-                false
-            );
-
-            SDL_EndGPUCopyPass(copy_pass);
-            SDL_SubmitGPUCommandBuffer(command_buffer);
-            SDL_DestroySurface(image);
-            SDL_ReleaseGPUTransferBuffer(gpu_device, texture_transfer_buffer);
-
             Self {
                 transfer_buffer,
                 data_buffer,
                 texture,
+                normal_map: null_mut(),
                 sampler,
                 count: 0,
                 size: 100000,
+                draw_count: 0,
+                cameras: Vec::new(),
             }
         }
     }
 
-    pub fn resize(&mut self, gpu_device: *mut SDL_GPUDevice) {
-        if self.count == self.size - 10000 {
-            self.size += 50000;
-            unsafe {
-                self.transfer_buffer = SDL_CreateGPUTransferBuffer(
-                    gpu_device,
-                    &(SDL_GPUTransferBufferCreateInfo {
-                        usage: SDL_GPU_TRANSFERBUFFERUSAGE_UPLOAD,
-                        size: (self.size * size_of::<Sprite>()) as u32,
-                        ..Default::default()
-                    })
-                );
+    /// Replaces the texture all sprites sample, e.g. with an atlas from `AsepriteSheet`. The
+    /// old texture is released and `Sprite::texture` rects now address the new one.
+    ///
+    /// Every sprite shares this one texture, there is no per-sprite texture. Sprites whose rects
+    /// point into the old texture draw whatever is at the same place in the new one, so all
+    /// sprite images must be packed into the same atlas.
+    pub fn set_texture(&mut self, gpu_device: *mut SDL_GPUDevice, texture: *mut SDL_GPUTexture) {
+        unsafe {
+            SDL_ReleaseGPUTexture(gpu_device, self.texture);
+        }
+        self.texture = texture;
+    }
 
-                self.data_buffer = SDL_CreateGPUBuffer(
-                    gpu_device,
-                    &(SDL_GPUBufferCreateInfo {
-                        usage: SDL_GPU_BUFFERUSAGE_GRAPHICS_STORAGE_READ,
-                        size: (self.size * size_of::<Sprite>()) as u32,
-                        ..Default::default()
-                    })
-                );
+    pub fn set_normal_map(&mut self, file_name: &str, gpu_device: *mut SDL_GPUDevice) {
+        let image = load_image(file_name, 4);
+
+        unsafe {
+            if self.normal_map != null_mut() {
+                SDL_ReleaseGPUTexture(gpu_device, self.normal_map);
             }
+
+            self.normal_map = upload_texture(gpu_device, image);
+            SDL_DestroySurface(image);
+        }
+    }
+}
+
+impl SpritesBuffer {
+    /// The sprites packed for `camera` this frame, `None` if it draws none.
+    pub fn camera(&self, camera: Entity) -> Option<&CameraSprites> {
+        self.cameras.iter().find(|sprites| sprites.camera == camera)
+    }
+
+    /// Grows the buffers to hold at least `count` sprites. This follows the packed count, which
+    /// can exceed the sprite count when several cameras see a sprite.
+    fn reserve(&mut self, gpu_device: *mut SDL_GPUDevice, count: usize) {
+        if count <= self.size {
+            return;
+        }
+
+        self.size = count.next_power_of_two();
+        unsafe {
+            SDL_ReleaseGPUTransferBuffer(gpu_device, self.transfer_buffer);
+            SDL_ReleaseGPUBuffer(gpu_device, self.data_buffer);
+
+            self.transfer_buffer = SDL_CreateGPUTransferBuffer(
+                gpu_device,
+                &(SDL_GPUTransferBufferCreateInfo {
+                    usage: SDL_GPU_TRANSFERBUFFERUSAGE_UPLOAD,
+                    size: (self.size * size_of::<Sprite>()) as u32,
+                    ..Default::default()
+                })
+            );
+
+            self.data_buffer = SDL_CreateGPUBuffer(
+                gpu_device,
+                &(SDL_GPUBufferCreateInfo {
+                    usage: SDL_GPU_BUFFERUSAGE_GRAPHICS_STORAGE_READ,
+                    size: (self.size * size_of::<Sprite>()) as u32,
+                    ..Default::default()
+                })
+            );
+        }
+    }
+}
+
+impl Default for SpriteCulling {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grid_cell_size: None,
+        }
+    }
+}
+
+impl BlendMode {
+    pub fn blend_state(&self) -> SDL_GPUColorTargetBlendState {
+        let (src_color, dst_color, src_alpha, dst_alpha) = match self {
+            BlendMode::Opaque => {
+                return SDL_GPUColorTargetBlendState::default();
+            }
+            BlendMode::Alpha =>
+                (
+                    SDL_GPU_BLENDFACTOR_SRC_ALPHA,
+                    SDL_GPU_BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                    SDL_GPU_BLENDFACTOR_ONE,
+                    SDL_GPU_BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                ),
+            BlendMode::Premultiplied =>
+                (
+                    SDL_GPU_BLENDFACTOR_ONE,
+                    SDL_GPU_BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                    SDL_GPU_BLENDFACTOR_ONE,
+                    SDL_GPU_BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                ),
+            BlendMode::Additive =>
+                (
+                    SDL_GPU_BLENDFACTOR_SRC_ALPHA,
+                    SDL_GPU_BLENDFACTOR_ONE,
+                    SDL_GPU_BLENDFACTOR_ZERO,
+                    SDL_GPU_BLENDFACTOR_ONE,
+                ),
+            BlendMode::Multiply =>
+                (
+                    SDL_GPU_BLENDFACTOR_DST_COLOR,
+                    SDL_GPU_BLENDFACTOR_ONE_MINUS_SRC_ALPHA,
+                    SDL_GPU_BLENDFACTOR_ZERO,
+                    SDL_GPU_BLENDFACTOR_ONE,
+                ),
+        };
+
+        SDL_GPUColorTargetBlendState {
+            src_color_blendfactor: src_color,
+            dst_color_blendfactor: dst_color,
+            color_blend_op: SDL_GPU_BLENDOP_ADD,
+            src_alpha_blendfactor: src_alpha,
+            dst_alpha_blendfactor: dst_alpha,
+            alpha_blend_op: SDL_GPU_BLENDOP_ADD,
+            enable_blend: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl TexturePipeline {
+    pub fn get(&mut self, blend_mode: BlendMode) -> *mut SDL_GPUGraphicsPipeline {
+        if let Some(pipeline) = self.pipelines.get(&blend_mode) {
+            return *pipeline;
+        }
+
+        unsafe {
+            let pipeline_create_info = SDL_GPUGraphicsPipelineCreateInfo {
+                target_info: SDL_GPUGraphicsPipelineTargetInfo {
+                    num_color_targets: 1,
+                    color_target_descriptions: &(SDL_GPUColorTargetDescription {
+                        format: self.format,
+                        blend_state: blend_mode.blend_state(),
+                    }),
+                    depth_stencil_format: DEPTH_FORMAT,
+                    has_depth_stencil_target: true,
+                    ..Default::default()
+                },
+                depth_stencil_state: SDL_GPUDepthStencilState {
+                    compare_op: SDL_GPU_COMPAREOP_LESS_OR_EQUAL,
+                    enable_depth_test: true,
+                    enable_depth_write: blend_mode == BlendMode::Opaque,
+                    ..Default::default()
+                },
+                multisample_state: SDL_GPUMultisampleState {
+                    sample_count: self.sample_count,
+                    ..Default::default()
+                },
+                primitive_type: SDL_GPU_PRIMITIVETYPE_TRIANGLELIST,
+                vertex_shader: self.vertex_shader,
+                fragment_shader: self.fragment_shader,
+                ..Default::default()
+            };
+
+            let pipeline = SDL_CreateGPUGraphicsPipeline(self.gpu_device, &pipeline_create_info);
+            if pipeline == null_mut() {
+                panic!("Failed to create Texture pipeline for {:?}", blend_mode);
+            }
+
+            self.pipelines.insert(blend_mode, pipeline);
+            pipeline
         }
     }
 }
@@ -217,7 +372,8 @@ impl Sprite {
             position,
             rotation: 0.0,
             scale: Vec2::new(32.0, 32.0),
-            padding: Vec2::new(0.0, 0.0),
+            normal_map: 0.0,
+            padding: 0.0,
             texture: Texture {
                 u: 0.0,
                 v: 0.0,
@@ -236,6 +392,102 @@ impl Sprite {
 
         sprite
     }
+
+    /// Makes the sprite sample `SpritesBuffer::normal_map` in the lighting normal pass.
+    pub fn with_normal_map(mut self) -> Self {
+        self.normal_map = 1.0;
+        self
+    }
+
+    /// Whether a world space point lies inside the sprite quad, which spans `scale` from
+    /// `position` and is rotated around it like in `texture.vert`.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let offset = point - self.position.truncate();
+        let local = Vec2::from_angle(-self.rotation).rotate(offset) / self.scale;
+
+        (0.0..=1.0).contains(&local.x) && (0.0..=1.0).contains(&local.y)
+    }
+
+    /// World space `(min, max)` of the rotated quad.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let rotation = Vec2::from_angle(self.rotation);
+        let x = rotation.rotate(Vec2::new(self.scale.x, 0.0));
+        let y = rotation.rotate(Vec2::new(0.0, self.scale.y));
+        let origin = self.position.truncate();
+
+        let corners = [origin, origin + x, origin + y, origin + x + y];
+        corners.iter().fold((corners[0], corners[0]), |(min, max), corner| {
+            (min.min(*corner), max.max(*corner))
+        })
+    }
+}
+
+struct SortedSprite {
+    sprite: Sprite,
+    blend_mode: BlendMode,
+    layer: Layer,
+    render_layers: RenderLayers,
+    id: u64,
+}
+
+/// Extends the last batch when the blend mode did not change, starts a new one otherwise.
+fn push_batch(batches: &mut Vec<SpriteBatch>, blend_mode: BlendMode, first: usize, count: usize) {
+    if let Some(last) = batches.last_mut() {
+        if last.blend_mode == blend_mode && last.first + last.count == first {
+            last.count += count;
+            return;
+        }
+    }
+
+    batches.push(SpriteBatch { blend_mode, first, count });
+}
+
+/// Sprite indices bucketed by the grid cells their bounds overlap.
+struct SpriteGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpriteGrid {
+    fn new(cell_size: f32, bounds: &[(Vec2, Vec2)]) -> Self {
+        let mut grid = Self { cell_size, cells: HashMap::new() };
+        for (index, (min, max)) in bounds.iter().enumerate() {
+            let (first, last) = (grid.cell(*min), grid.cell(*max));
+            for y in first.1..=last.1 {
+                for x in first.0..=last.0 {
+                    grid.cells.entry((x, y)).or_default().push(index);
+                }
+            }
+        }
+        grid
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        let cell = (point / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+
+    /// Sprites in the cells overlapping the rect in ascending order, `None` when the rect
+    /// covers more cells than there are sprites and testing all of them is cheaper.
+    fn query(&self, min: Vec2, max: Vec2, sprite_count: usize) -> Option<Vec<usize>> {
+        let (first, last) = (self.cell(min), self.cell(max));
+        let cell_count = (last.0 as i64 - first.0 as i64 + 1) * (last.1 as i64 - first.1 as i64 + 1);
+        if cell_count > sprite_count as i64 {
+            return None;
+        }
+
+        let mut indices = Vec::new();
+        for y in first.1..=last.1 {
+            for x in first.0..=last.0 {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    indices.extend_from_slice(cell);
+                }
+            }
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        Some(indices)
+    }
 }
 
 impl Module for SpritesModule {
@@ -243,6 +495,14 @@ impl Module for SpritesModule {
         world.component::<Sprite>();
         world.component::<SpritesBuffer>();
         world.component::<TexturePipeline>();
+        world.component::<BlendMode>();
+        world.component::<Layer>();
+        world.component::<RenderLayers>();
+        world.component::<SpriteCulling>();
+        world.component::<SpriteStats>();
+
+        world.set(SpriteCulling::default());
+        world.set(SpriteStats::default());
 
         world.get::<&GpuApi>(|gpu_api| {
             let gpu_device = gpu_api.gpu_device;
@@ -250,165 +510,249 @@ impl Module for SpritesModule {
             world.set(sprites_buffer);
         });
 
-        let sprites_query = world.query::<&Sprite>().set_cached().build();
+        let sprites_query = world
+            .query::<(&Sprite, Option<&BlendMode>, Option<&Layer>, Option<&RenderLayers>)>()
+            .set_cached()
+            .build();
         observer!("init_texture_shader", world, ShadersInitEvent, flecs::Any).each_iter(|it, _, _| {
             let event = &*it.param();
             let world = it.world();
             let gpu_device = event.gpu_device;
             let window = event.window;
 
-            let vertex_shader = load_shader(gpu_device, "texture.vert", 0, 1, 1, 0).unwrap();
-            let fragment_shader = load_shader(gpu_device, "texture.frag", 1, 0, 0, 0).unwrap();
+            // Sprites are skipped while there is no `TexturePipeline`
+            let vertex_shader = match load_shader(gpu_device, "texture.vert", 0, 1, 1, 0) {
+                Ok(shader) => shader,
+                Err(error) => {
+                    println!("Sprites disabled: {}", error);
+                    return;
+                }
+            };
+            let fragment_shader = match load_shader(gpu_device, "texture.frag", 1, 0, 0, 0) {
+                Ok(shader) => shader,
+                Err(error) => {
+                    unsafe {
+                        SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                    }
+                    println!("Sprites disabled: {}", error);
+                    return;
+                }
+            };
 
             unsafe {
-                let pipeline_create_info = SDL_GPUGraphicsPipelineCreateInfo {
-                    target_info: SDL_GPUGraphicsPipelineTargetInfo {
-                        num_color_targets: 1,
-                        color_target_descriptions: &(SDL_GPUColorTargetDescription {
-                            format: SDL_GetGPUSwapchainTextureFormat(gpu_device, window),
-                            ..Default::default()
-                        }),
-                        ..Default::default()
-                    },
-                    primitive_type: SDL_GPU_PRIMITIVETYPE_TRIANGLELIST,
+                let mut pipeline = TexturePipeline {
+                    gpu_device,
+                    format: SDL_GetGPUSwapchainTextureFormat(gpu_device, window),
+                    sample_count: event.sample_count,
                     vertex_shader,
                     fragment_shader,
-                    ..Default::default()
+                    pipelines: HashMap::new(),
                 };
 
-                let pipeline = SDL_CreateGPUGraphicsPipeline(gpu_device, &pipeline_create_info);
-                if pipeline == null_mut() {
-                    panic!("Failed to create Texture pipeline");
-                }
-
-                world.set(TexturePipeline(pipeline));
-
-                SDL_ReleaseGPUShader(gpu_device, vertex_shader);
-                SDL_ReleaseGPUShader(gpu_device, fragment_shader);
+                // The common case is created up front, the others when a sprite first uses them
+                pipeline.get(BlendMode::Alpha);
+                world.set(pipeline);
 
                 println!("Setting Texture Pipeline");
             }
         });
 
-        system!("sprite_render_pipeline", world, &GpuApi($), &Window($), &mut Camera($), &TexturePipeline($), &SpritesBuffer($))
-            .kind::<PreUpdate>()
-            .each_iter(move |_it, _, (gpu_api, window, camera, pipeline, sprite_buffer)| unsafe {
+        let cameras_query = world.query::<(&Camera, &CameraView)>().set_cached().build();
+
+        system!(
+            "sprite_upload",
+            world,
+            &GpuApi($),
+            &GpuFrame($),
+            &SpriteCulling($),
+            &mut SpriteStats($),
+            &mut SpritesBuffer($)
+        )
+            .kind::<PreStore>()
+            .each(move |(gpu_api, frame, culling, stats, sprite_buffer)| unsafe {
                 let gpu_device = gpu_api.gpu_device;
-                let color = gpu_api.color;
-                let window = window.0;
+                sprite_buffer.draw_count = 0;
+                sprite_buffer.cameras.clear();
+                *stats = SpriteStats::default();
 
-                let cmd_buf = SDL_AcquireGPUCommandBuffer(gpu_device);
-                if cmd_buf == null_mut() {
-                    let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
-                    panic!("Failed to acquire GPU command buffer: {:?}", error);
+                if frame.command_buffer == null_mut() || sprites_query.count() == 0 {
+                    return;
                 }
 
-                let mut swapchain_texture: *mut SDL_GPUTexture = null_mut();
-                if
-                    !SDL_WaitAndAcquireGPUSwapchainTexture(
-                        cmd_buf,
-                        window,
-                        &mut swapchain_texture,
-                        null_mut(),
-                        null_mut()
-                    )
-                {
-                    let error = CStr::from_ptr(SDL_GetError()).to_str().unwrap();
-                    panic!("Failed to wait and acquire GPU swapchain texture: {:?}", error);
+                // Opaque sprites first, then everything back-to-front by z and layer. The entity id
+                // breaks ties so equal keys keep the same order every frame.
+                let mut sorted: Vec<SortedSprite> = Vec::with_capacity(sprite_buffer.count);
+                sprites_query.each_entity(|entity, (sprite, blend_mode, layer, render_layers)| {
+                    sorted.push(SortedSprite {
+                        sprite: *sprite,
+                        blend_mode: blend_mode.copied().unwrap_or_default(),
+                        layer: layer.copied().unwrap_or_default(),
+                        render_layers: render_layers.copied().unwrap_or_default(),
+                        id: *entity.id(),
+                    });
+                });
+                sorted.sort_unstable_by(|a, b| {
+                    (b.blend_mode == BlendMode::Opaque)
+                        .cmp(&(a.blend_mode == BlendMode::Opaque))
+                        .then(a.sprite.position.z.total_cmp(&b.sprite.position.z))
+                        .then(a.layer.cmp(&b.layer))
+                        .then(a.id.cmp(&b.id))
+                });
+                stats.sprites = sorted.len();
+
+                // Sprites per render layer mask, so cameras count the ones on their layers
+                // without going over every sprite
+                let mut layer_counts: Vec<(u32, usize)> = Vec::new();
+                for entry in sorted.iter() {
+                    let mask = entry.render_layers.0;
+                    match layer_counts.iter_mut().find(|(layers, _)| *layers == mask) {
+                        Some((_, count)) => {
+                            *count += 1;
+                        }
+                        None => layer_counts.push((mask, 1)),
+                    }
                 }
 
-                if swapchain_texture != null_mut() {
-                    let mut color_target_info = SDL_GPUColorTargetInfo::default();
-                    color_target_info.texture = swapchain_texture;
-                    color_target_info.clear_color = SDL_FColor {
-                        r: color.0,
-                        g: color.1,
-                        b: color.2,
-                        a: 1.0,
+                let bounds: Vec<(Vec2, Vec2)> = sorted
+                    .iter()
+                    .map(|entry| entry.sprite.bounds())
+                    .collect();
+                let grid = culling.grid_cell_size
+                    .filter(|cell_size| culling.enabled && *cell_size > 0.0)
+                    .map(|cell_size| SpriteGrid::new(cell_size, &bounds));
+
+                // Indices into `sorted` each camera draws, ascending so the draw order holds
+                let mut visible: Vec<(Entity, Vec<usize>)> = Vec::new();
+                cameras_query.each_entity(|entity, (camera, view)| {
+                    let on_layers = |index: &usize| {
+                        (sorted[*index].render_layers.0 & view.layer_mask) != 0
+                    };
+                    let rect = camera.visible_rect().filter(|_| culling.enabled);
+
+                    let indices: Vec<usize> = match rect {
+                        Some((min, max)) => {
+                            let overlaps = |index: &usize| {
+                                let (sprite_min, sprite_max) = bounds[*index];
+                                sprite_min.cmple(max).all() && sprite_max.cmpge(min).all()
+                            };
+                            let candidates = grid
+                                .as_ref()
+                                .and_then(|grid| grid.query(min, max, sorted.len()));
+
+                            let on_camera: usize = layer_counts
+                                .iter()
+                                .filter(|(layers, _)| (layers & view.layer_mask) != 0)
+                                .map(|(_, count)| count)
+                                .sum();
+                            let indices: Vec<usize> = match candidates {
+                                Some(candidates) =>
+                                    candidates.into_iter().filter(on_layers).filter(overlaps).collect(),
+                                None => (0..sorted.len()).filter(on_layers).filter(overlaps).collect(),
+                            };
+                            stats.culled += on_camera - indices.len();
+                            indices
+                        }
+                        None => (0..sorted.len()).filter(on_layers).collect(),
                     };
-                    color_target_info.load_op = SDL_GPU_LOADOP_CLEAR;
-                    color_target_info.store_op = SDL_GPU_STOREOP_STORE;
-
-                    let render_pass = SDL_BeginGPURenderPass(
-                        cmd_buf,
-                        &color_target_info,
-                        1,
-                        null_mut()
-                    );
-                    if sprites_query.count() != 0 {
-                        let data_ptr = SDL_MapGPUTransferBuffer(
-                            gpu_device,
-                            sprite_buffer.transfer_buffer,
-                            false
-                        );
 
-                        sprites_query.run(|mut it| {
-                            while it.next() {
-                                let s = &it.field::<Sprite>(0).unwrap()[..];
-                                SDL_memcpy(
-                                    data_ptr,
-                                    s.as_ptr() as *const c_void,
-                                    s.len() * size_of::<Sprite>()
-                                );
-
-                                SDL_UnmapGPUTransferBuffer(
-                                    gpu_device,
-                                    sprite_buffer.transfer_buffer
-                                );
-
-                                let copy_pass = SDL_BeginGPUCopyPass(cmd_buf);
-                                SDL_UploadToGPUBuffer(
-                                    copy_pass,
-                                    &(SDL_GPUTransferBufferLocation {
-                                        transfer_buffer: sprite_buffer.transfer_buffer,
-                                        offset: 0,
-                                    }),
-                                    &(SDL_GPUBufferRegion {
-                                        buffer: sprite_buffer.data_buffer,
-                                        offset: 0,
-                                        size: (s.len() * size_of::<Sprite>()) as u32,
-                                    }),
-                                    true
-                                );
-                                SDL_EndGPUCopyPass(copy_pass);
-
-                                SDL_BindGPUGraphicsPipeline(render_pass, pipeline.0);
-                                SDL_BindGPUVertexStorageBuffers(
-                                    render_pass,
-                                    0,
-                                    &sprite_buffer.data_buffer,
-                                    1
-                                );
-                                SDL_BindGPUFragmentSamplers(
-                                    render_pass,
-                                    0,
-                                    &(SDL_GPUTextureSamplerBinding {
-                                        texture: sprite_buffer.texture,
-                                        sampler: sprite_buffer.sampler,
-                                    }),
-                                    1
-                                );
-                                SDL_PushGPUVertexUniformData(
-                                    cmd_buf,
-                                    0,
-                                    &mut camera.0 as *mut _ as *mut c_void,
-                                    size_of::<Mat4>() as u32
-                                );
-                                SDL_DrawGPUPrimitives(render_pass, (s.len() * 6) as u32, 1, 0, 0);
-                            }
-                        });
+                    stats.drawn += indices.len();
+                    if !indices.is_empty() {
+                        visible.push((entity.id(), indices));
                     }
+                });
 
-                    SDL_EndGPURenderPass(render_pass);
+                let count = stats.drawn;
+                if count == 0 {
+                    return;
                 }
+                sprite_buffer.reserve(gpu_device, count);
 
-                SDL_SubmitGPUCommandBuffer(cmd_buf);
-            });
+                let data_ptr = SDL_MapGPUTransferBuffer(
+                    gpu_device,
+                    sprite_buffer.transfer_buffer,
+                    true
+                ) as *mut Sprite;
+
+                let mut offset = 0;
+                for (camera, indices) in visible {
+                    let mut sprites = CameraSprites {
+                        camera,
+                        first: offset,
+                        count: indices.len(),
+                        batches: Vec::new(),
+                    };
+                    for index in indices {
+                        let entry = &sorted[index];
+                        data_ptr.add(offset).write(entry.sprite);
+                        push_batch(&mut sprites.batches, entry.blend_mode, offset, 1);
+                        offset += 1;
+                    }
+                    sprite_buffer.cameras.push(sprites);
+                }
 
-        system!("resize_sprite_buffer", world, &GpuApi($), &mut SpritesBuffer)
-            .kind::<PreStore>()
-            .each(|(gpu_api, sprites_buffer)| {
-                sprites_buffer.resize(gpu_api.gpu_device);
+                SDL_UnmapGPUTransferBuffer(gpu_device, sprite_buffer.transfer_buffer);
+
+                let copy_pass = SDL_BeginGPUCopyPass(frame.command_buffer);
+                SDL_UploadToGPUBuffer(
+                    copy_pass,
+                    &(SDL_GPUTransferBufferLocation {
+                        transfer_buffer: sprite_buffer.transfer_buffer,
+                        offset: 0,
+                    }),
+                    &(SDL_GPUBufferRegion {
+                        buffer: sprite_buffer.data_buffer,
+                        offset: 0,
+                        size: (count * size_of::<Sprite>()) as u32,
+                    }),
+                    true
+                );
+                SDL_EndGPUCopyPass(copy_pass);
+
+                sprite_buffer.draw_count = count;
             });
+
+        observer!("sprite_render", world, RenderEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+
+            world.get::<(Option<&mut TexturePipeline>, &SpritesBuffer)>(
+                |(pipeline, sprite_buffer)| unsafe {
+                    let Some(pipeline) = pipeline else {
+                        return;
+                    };
+                    let render_pass = event.render_pass;
+                    let Some(sprites) = sprite_buffer.camera(event.camera) else {
+                        return;
+                    };
+
+                    for batch in sprites.batches.iter() {
+                        SDL_BindGPUGraphicsPipeline(render_pass, pipeline.get(batch.blend_mode));
+                        SDL_BindGPUVertexStorageBuffers(render_pass, 0, &sprite_buffer.data_buffer, 1);
+                        SDL_BindGPUFragmentSamplers(
+                            render_pass,
+                            0,
+                            &(SDL_GPUTextureSamplerBinding {
+                                texture: sprite_buffer.texture,
+                                sampler: sprite_buffer.sampler,
+                            }),
+                            1
+                        );
+                        SDL_PushGPUVertexUniformData(
+                            event.command_buffer,
+                            0,
+                            &event.view_projection as *const _ as *const c_void,
+                            size_of::<Mat4>() as u32
+                        );
+                        SDL_DrawGPUPrimitives(
+                            render_pass,
+                            (batch.count * 6) as u32,
+                            1,
+                            (batch.first * 6) as u32,
+                            0
+                        );
+                    }
+                }
+            );
+        });
     }
 }
@@ -0,0 +1,319 @@
+use std::{ ffi::CStr, ptr::null };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::{ OnLoad, PreUpdate } }, Entity, EntityView, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, SystemAPI },
+};
+use sdl3_sys::{
+    error::SDL_GetError,
+    keyboard::{ SDL_SetTextInputArea, SDL_StartTextInput, SDL_StopTextInput },
+    rect::SDL_Rect,
+    scancode::*,
+};
+
+use crate::{
+    events::{ KeyboardEvent, TextEditingEvent, TextInputEvent },
+    modules::input::InputState,
+    window::Window,
+};
+
+/// Routes SDL text input to the focused `TextField`. Text input (and with it the IME) is only
+/// enabled while `TextInputFocus` points at a field, SDL sends no text events otherwise.
+///
+/// While a field has focus the keyboard is released in `InputState`, so typing does not trigger
+/// `InputMap` actions. Must be imported after `InputModule` and `ReplayModule`.
+#[derive(Component)]
+pub struct TextInputModule;
+
+/// Editable text on an entity. `cursor` and the composition cursor count characters, not bytes.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TextField {
+    pub text: String,
+    pub cursor: usize,
+    /// Text the IME is still composing, it is not part of `text` until committed.
+    pub composition: String,
+    pub composition_cursor: usize,
+    pub composition_selection: usize,
+    pub max_length: Option<usize>,
+    /// Where the field is in window coordinates, the IME places its candidate list next to it.
+    pub area: Option<SDL_Rect>,
+    /// Width of a character in window pixels for monospaced text, moves the candidate list to
+    /// the caret inside `area`. At 0 it stays at the start of the area.
+    pub char_width: f32,
+}
+
+/// The field receiving text input, set it to start text entry and clear it to stop.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct TextInputFocus {
+    pub entity: Option<Entity>,
+    /// The field SDL text input was started for, managed by the module.
+    active: Option<Entity>,
+    /// Area and caret offset last passed to `SDL_SetTextInputArea`.
+    input_area: Option<(Option<(i32, i32, i32, i32)>, i32)>,
+}
+
+/// Emitted after a key press, committed text or composition changed a field.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TextFieldChangedEvent {
+    pub entity: Entity,
+}
+
+/// Emitted when Return is pressed in the focused field.
+#[derive(Component, Clone, Debug)]
+pub struct TextFieldSubmitEvent {
+    pub entity: Entity,
+    pub text: String,
+}
+
+impl TextField {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_owned(),
+            cursor: text.chars().count(),
+            ..Default::default()
+        }
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map(|(index, _)| index)
+            .unwrap_or(self.text.len())
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        let length = self.text.chars().count();
+        let allowed = match self.max_length {
+            Some(max_length) => max_length.saturating_sub(length),
+            None => usize::MAX,
+        };
+
+        let inserted: String = text.chars().take(allowed).collect();
+        let index = self.byte_index(self.cursor);
+        self.text.insert_str(index, &inserted);
+        self.cursor += inserted.chars().count();
+    }
+
+    /// Caret position in pixels from the left of `area`, inside the composition while the IME
+    /// is composing.
+    fn caret_offset(&self) -> i32 {
+        let caret = self.cursor + self.composition_cursor;
+        ((caret as f32) * self.char_width).round() as i32
+    }
+
+    pub fn delete_backward(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+
+        self.cursor -= 1;
+        let index = self.byte_index(self.cursor);
+        self.text.remove(index);
+    }
+
+    pub fn delete_forward(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let index = self.byte_index(self.cursor);
+            self.text.remove(index);
+        }
+    }
+}
+
+impl TextInputFocus {
+    pub fn focus(&mut self, entity: Entity) {
+        self.entity = Some(entity);
+    }
+
+    pub fn clear(&mut self) {
+        self.entity = None;
+    }
+
+    pub fn is_focused(&self, entity: Entity) -> bool {
+        self.entity == Some(entity)
+    }
+}
+
+/// Runs `edit` on the focused field, if there is one.
+fn edit_focused(world: &World, edit: impl FnOnce(EntityView, &mut TextField)) {
+    let mut focused = None;
+    world.get::<&TextInputFocus>(|focus| {
+        focused = focus.entity;
+    });
+
+    let Some(entity) = focused else {
+        return;
+    };
+
+    let entity = world.entity_from_id(entity);
+    if !entity.is_alive() {
+        return;
+    }
+
+    entity.get::<Option<&mut TextField>>(|field| {
+        if let Some(field) = field {
+            edit(entity, field);
+        }
+    });
+}
+
+impl Module for TextInputModule {
+    fn module(world: &World) {
+        world.component::<TextField>();
+        world.component::<TextInputFocus>();
+        world.component::<TextFieldChangedEvent>();
+        world.component::<TextFieldSubmitEvent>();
+
+        world.set(TextInputFocus::default());
+
+        observer!("text_field_input", world, TextInputEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+
+            edit_focused(&world, |entity, field| {
+                field.composition.clear();
+                field.insert(&event.text);
+                world.event().entity(flecs::Any).emit(&TextFieldChangedEvent {
+                    entity: entity.id(),
+                });
+            });
+        });
+
+        observer!("text_field_editing", world, TextEditingEvent, flecs::Any).each_iter(
+            |it, _, _| {
+                let event = &*it.param();
+                let world = it.world();
+
+                edit_focused(&world, |entity, field| {
+                    field.composition = event.text.clone();
+                    field.composition_cursor = event.start.max(0) as usize;
+                    field.composition_selection = event.length.max(0) as usize;
+                    world.event().entity(flecs::Any).emit(&TextFieldChangedEvent {
+                        entity: entity.id(),
+                    });
+                });
+            }
+        );
+
+        observer!("text_field_keys", world, KeyboardEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+            if !event.down {
+                return;
+            }
+
+            let mut blur = false;
+            edit_focused(&world, |entity, field| {
+                // Keys belong to the IME while it is composing
+                if !field.composition.is_empty() {
+                    return;
+                }
+
+                let length = field.text.chars().count();
+                match event.scancode {
+                    SDL_SCANCODE_BACKSPACE => field.delete_backward(),
+                    SDL_SCANCODE_DELETE => field.delete_forward(),
+                    SDL_SCANCODE_LEFT => {
+                        field.cursor = field.cursor.saturating_sub(1);
+                        return;
+                    }
+                    SDL_SCANCODE_RIGHT => {
+                        field.cursor = (field.cursor + 1).min(length);
+                        return;
+                    }
+                    SDL_SCANCODE_HOME => {
+                        field.cursor = 0;
+                        return;
+                    }
+                    SDL_SCANCODE_END => {
+                        field.cursor = length;
+                        return;
+                    }
+                    SDL_SCANCODE_RETURN | SDL_SCANCODE_KP_ENTER => {
+                        world.event().entity(flecs::Any).emit(&TextFieldSubmitEvent {
+                            entity: entity.id(),
+                            text: field.text.clone(),
+                        });
+                        return;
+                    }
+                    SDL_SCANCODE_ESCAPE => {
+                        blur = true;
+                        return;
+                    }
+                    _ => {
+                        return;
+                    }
+                }
+
+                world.event().entity(flecs::Any).emit(&TextFieldChangedEvent {
+                    entity: entity.id(),
+                });
+            });
+
+            if blur {
+                world.get::<&mut TextInputFocus>(|focus| focus.clear());
+            }
+        });
+
+        system!("text_input_focus", world, &Window($), &mut TextInputFocus($))
+            .kind::<PreUpdate>()
+            .each_iter(|it, _, (window, focus)| unsafe {
+                let world = it.world();
+
+                // Focus on a destroyed entity or one without a field is dropped
+                if let Some(entity) = focus.entity {
+                    let entity = world.entity_from_id(entity);
+                    if !entity.is_alive() || !entity.has::<TextField>() {
+                        focus.entity = None;
+                    }
+                }
+
+                if focus.entity != focus.active {
+                    let Some(entity) = focus.entity else {
+                        SDL_StopTextInput(window.0);
+                        focus.active = None;
+                        focus.input_area = None;
+                        return;
+                    };
+
+                    if focus.active.is_none() && !SDL_StartTextInput(window.0) {
+                        println!(
+                            "Failed to start text input: {}",
+                            CStr::from_ptr(SDL_GetError()).to_string_lossy()
+                        );
+                        return;
+                    }
+                    focus.active = Some(entity);
+                    focus.input_area = None;
+                }
+
+                let Some(entity) = focus.active else {
+                    return;
+                };
+
+                // Follows the caret, only calling into SDL when it moved
+                world.entity_from_id(entity).get::<&TextField>(|field| {
+                    let caret = field.caret_offset();
+                    let input_area = (field.area.map(|area| (area.x, area.y, area.w, area.h)), caret);
+                    if focus.input_area == Some(input_area) {
+                        return;
+                    }
+                    focus.input_area = Some(input_area);
+
+                    let area = field.area.as_ref().map_or(null(), |area| area as *const _);
+                    SDL_SetTextInputArea(window.0, area, caret);
+                });
+            });
+
+        // Runs after the keyboard was sampled and replayed, before actions are evaluated and the
+        // input is recorded
+        system!("release_keys_while_typing", world, &TextInputFocus($), &mut InputState($))
+            .kind::<OnLoad>()
+            .each(|(focus, state)| {
+                if focus.entity.is_some() {
+                    state.keys.fill(false);
+                }
+            });
+    }
+}
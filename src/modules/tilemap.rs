@@ -0,0 +1,509 @@
+use std::{ ffi::c_void, ptr::null_mut };
+
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PreStore }, World, WorldGet },
+    macros::{ observer, system, Component },
+    prelude::{ Module, QueryAPI, QueryBuilderImpl, SystemAPI },
+};
+use glam::{ Mat4, Vec2, Vec3, Vec4 };
+use sdl3_sys::{ gpu::*, surface::SDL_DestroySurface };
+
+use crate::{
+    camera::{ visible_rect, RenderLayers },
+    gpu::{ upload_texture, GpuApi, GpuFrame, RenderEvent, ShadersInitEvent, DEPTH_FORMAT },
+    load_image,
+    load_shader,
+};
+
+/// Grids of tiles drawn from a tileset texture, one `TileLayer` component per layer. Layers are
+/// split into chunks of `CHUNK_SIZE` by `CHUNK_SIZE` cells, each with its own GPU buffer of
+/// tile indices that is only uploaded again after one of its tiles changed. Chunks outside a
+/// camera's visible rect are not drawn.
+///
+/// Tiles are alpha tested and write depth, so they sort against sprites by `z`. Must be
+/// imported before `SpritesModule` so the tiles are drawn before the transparent sprites.
+#[derive(Component)]
+pub struct TilemapModule;
+
+/// Cells per chunk side.
+pub const CHUNK_SIZE: u32 = 32;
+
+/// Tile flags in the top bits of a cell, the same as Tiled's global tile ids.
+pub const TILE_FLIP_HORIZONTAL: u32 = 0x8000_0000;
+pub const TILE_FLIP_VERTICAL: u32 = 0x4000_0000;
+/// Swaps the x and y axes of the tile, applied before the other flips.
+pub const TILE_FLIP_DIAGONAL: u32 = 0x2000_0000;
+pub const TILE_FLAGS: u32 = TILE_FLIP_HORIZONTAL | TILE_FLIP_VERTICAL | TILE_FLIP_DIAGONAL;
+
+/// A texture cut into equally sized tiles, numbered left to right, top to bottom from 0.
+#[derive(Clone, Copy, Debug)]
+pub struct Tileset {
+    pub texture: *mut SDL_GPUTexture,
+    pub columns: u32,
+    pub tile_count: u32,
+    /// In texture pixels.
+    pub image_size: Vec2,
+    /// In texture pixels.
+    pub tile_size: Vec2,
+    /// Pixels around the tiles at the edges of the image.
+    pub margin: f32,
+    /// Pixels between two tiles.
+    pub spacing: f32,
+}
+
+struct TileChunk {
+    buffer: *mut SDL_GPUBuffer,
+    /// Cell of the layer at the chunk's top left corner.
+    origin: (u32, u32),
+    /// Smaller than `CHUNK_SIZE` at the right and bottom edges of the layer.
+    width: u32,
+    height: u32,
+    dirty: bool,
+    /// Number of non-empty cells, empty chunks are skipped.
+    tile_count: u32,
+}
+
+#[derive(Component)]
+pub struct TileLayer {
+    pub tileset: Tileset,
+    /// World position of the top left corner of cell (0, 0), `z` like `Sprite::position`.
+    pub position: Vec3,
+    /// World size of one cell.
+    pub cell_size: Vec2,
+    /// Multiplied with the tileset texture.
+    pub color: Vec4,
+    width: u32,
+    height: u32,
+    /// 0 is empty, otherwise tileset index + 1 combined with the `TILE_FLIP_*` flags.
+    tiles: Vec<u32>,
+    chunks: Vec<TileChunk>,
+    chunk_columns: u32,
+    transfer_buffer: *mut SDL_GPUTransferBuffer,
+}
+
+#[derive(Component)]
+pub struct TilemapPipeline {
+    pub pipeline: *mut SDL_GPUGraphicsPipeline,
+    pub sampler: *mut SDL_GPUSampler,
+}
+
+#[repr(C)]
+struct TileVertexUniforms {
+    view_projection: Mat4,
+    origin: Vec4,
+    tile_size: Vec4,
+    tile_uv: Vec4,
+    uv_offset: Vec4,
+}
+
+unsafe impl Send for Tileset {}
+unsafe impl Sync for Tileset {}
+
+unsafe impl Send for TileLayer {}
+unsafe impl Sync for TileLayer {}
+
+unsafe impl Send for TilemapPipeline {}
+unsafe impl Sync for TilemapPipeline {}
+
+impl Tileset {
+    /// Fails unless the image and tile sizes are positive and the margin and spacing are not
+    /// negative.
+    pub fn new(
+        texture: *mut SDL_GPUTexture,
+        image_size: Vec2,
+        tile_size: Vec2,
+        margin: f32,
+        spacing: f32
+    ) -> Result<Self, String> {
+        // Written so NaN fails too
+        if !(image_size.cmpgt(Vec2::ZERO).all() && tile_size.cmpgt(Vec2::ZERO).all()) {
+            return Err(format!("invalid tileset with image size {} and tile size {}", image_size, tile_size));
+        }
+        if !(margin >= 0.0 && spacing >= 0.0) {
+            return Err(format!("invalid tileset with margin {} and spacing {}", margin, spacing));
+        }
+
+        let cells = ((image_size - margin * 2.0 + spacing) / (tile_size + spacing)).floor().max(Vec2::ONE);
+
+        Ok(Self {
+            texture,
+            columns: cells.x as u32,
+            tile_count: (cells.x * cells.y) as u32,
+            image_size,
+            tile_size,
+            margin,
+            spacing,
+        })
+    }
+
+    /// Loads `Images/<file_name>` like `SpritesBuffer::new` and cuts it into tiles without
+    /// margin or spacing.
+    pub fn load(gpu_device: *mut SDL_GPUDevice, file_name: &str, tile_size: Vec2) -> Result<Self, String> {
+        let image = load_image(file_name, 4);
+
+        unsafe {
+            let image_size = Vec2::new((*image).w as f32, (*image).h as f32);
+            let texture = upload_texture(gpu_device, image);
+            SDL_DestroySurface(image);
+
+            Self::new(texture, image_size, tile_size, 0.0, 0.0)
+                .map_err(|error| format!("{}: {}", file_name, error))
+        }
+    }
+
+    /// `(tile size, distance between tiles)` and the uv of the first tile, for `tilemap.vert`.
+    fn uv_layout(&self) -> (Vec4, Vec4) {
+        let tile = self.tile_size / self.image_size;
+        let stride = (self.tile_size + self.spacing) / self.image_size;
+        let offset = Vec2::splat(self.margin) / self.image_size;
+
+        (Vec4::new(tile.x, tile.y, stride.x, stride.y), offset.extend(0.0).extend(0.0))
+    }
+}
+
+impl TileLayer {
+    /// An empty layer of `width` by `height` cells.
+    pub fn new(tileset: Tileset, width: u32, height: u32, cell_size: Vec2, position: Vec3) -> Self {
+        let chunk_columns = width.div_ceil(CHUNK_SIZE);
+        let chunk_rows = height.div_ceil(CHUNK_SIZE);
+
+        let mut chunks = Vec::with_capacity((chunk_columns * chunk_rows) as usize);
+        for row in 0..chunk_rows {
+            for column in 0..chunk_columns {
+                let origin = (column * CHUNK_SIZE, row * CHUNK_SIZE);
+                chunks.push(TileChunk {
+                    buffer: null_mut(),
+                    origin,
+                    width: CHUNK_SIZE.min(width - origin.0),
+                    height: CHUNK_SIZE.min(height - origin.1),
+                    dirty: false,
+                    tile_count: 0,
+                });
+            }
+        }
+
+        Self {
+            tileset,
+            position,
+            cell_size,
+            color: Vec4::ONE,
+            width,
+            height,
+            tiles: vec![0; (width * height) as usize],
+            chunks,
+            chunk_columns,
+            transfer_buffer: null_mut(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The cell value at `(x, y)`, 0 for empty cells and cells outside the layer.
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    /// Sets a cell to `tile`, see `tiles`. Cells outside the layer are ignored.
+    pub fn set(&mut self, x: u32, y: u32, tile: u32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let cell = &mut self.tiles[(y * self.width + x) as usize];
+        if *cell == tile {
+            return;
+        }
+
+        let chunk = &mut self.chunks[((y / CHUNK_SIZE) * self.chunk_columns + x / CHUNK_SIZE) as usize];
+        match (*cell == 0, tile == 0) {
+            (true, false) => {
+                chunk.tile_count += 1;
+            }
+            (false, true) => {
+                chunk.tile_count -= 1;
+            }
+            _ => {}
+        }
+        chunk.dirty = true;
+        *cell = tile;
+    }
+
+    pub fn fill(&mut self, tile: u32) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.set(x, y, tile);
+            }
+        }
+    }
+
+    /// The cell containing a world position, `None` outside the layer.
+    pub fn cell_at(&self, world_position: Vec2) -> Option<(u32, u32)> {
+        let cell = ((world_position - self.position.truncate()) / self.cell_size).floor();
+        if cell.x < 0.0 || cell.y < 0.0 || cell.x >= (self.width as f32) || cell.y >= (self.height as f32) {
+            return None;
+        }
+        Some((cell.x as u32, cell.y as u32))
+    }
+
+    /// Frees the GPU buffers, the layer can not be drawn afterwards.
+    pub fn release(&mut self, gpu_device: *mut SDL_GPUDevice) {
+        unsafe {
+            for chunk in self.chunks.iter_mut() {
+                if chunk.buffer != null_mut() {
+                    SDL_ReleaseGPUBuffer(gpu_device, chunk.buffer);
+                    chunk.buffer = null_mut();
+                }
+            }
+            if self.transfer_buffer != null_mut() {
+                SDL_ReleaseGPUTransferBuffer(gpu_device, self.transfer_buffer);
+                self.transfer_buffer = null_mut();
+            }
+        }
+    }
+
+    /// Copies the cells of every dirty chunk to its GPU buffer, creating buffers on first use.
+    unsafe fn upload(&mut self, gpu_device: *mut SDL_GPUDevice, copy_pass: *mut SDL_GPUCopyPass) {
+        let chunk_bytes = CHUNK_SIZE * CHUNK_SIZE * (size_of::<u32>() as u32);
+        if self.transfer_buffer == null_mut() {
+            self.transfer_buffer = SDL_CreateGPUTransferBuffer(
+                gpu_device,
+                &(SDL_GPUTransferBufferCreateInfo {
+                    usage: SDL_GPU_TRANSFERBUFFERUSAGE_UPLOAD,
+                    size: chunk_bytes,
+                    ..Default::default()
+                })
+            );
+        }
+
+        for chunk in self.chunks.iter_mut().filter(|chunk| chunk.dirty) {
+            if chunk.buffer == null_mut() {
+                chunk.buffer = SDL_CreateGPUBuffer(
+                    gpu_device,
+                    &(SDL_GPUBufferCreateInfo {
+                        usage: SDL_GPU_BUFFERUSAGE_GRAPHICS_STORAGE_READ,
+                        size: chunk_bytes,
+                        ..Default::default()
+                    })
+                );
+            }
+
+            // Cycling hands out a fresh transfer buffer while earlier chunks are still in flight
+            let data_ptr = SDL_MapGPUTransferBuffer(gpu_device, self.transfer_buffer, true) as *mut u32;
+            for row in 0..chunk.height {
+                let first = ((chunk.origin.1 + row) * self.width + chunk.origin.0) as usize;
+                let cells = &self.tiles[first..first + (chunk.width as usize)];
+                data_ptr
+                    .add((row * chunk.width) as usize)
+                    .copy_from_nonoverlapping(cells.as_ptr(), cells.len());
+            }
+            SDL_UnmapGPUTransferBuffer(gpu_device, self.transfer_buffer);
+
+            SDL_UploadToGPUBuffer(
+                copy_pass,
+                &(SDL_GPUTransferBufferLocation {
+                    transfer_buffer: self.transfer_buffer,
+                    offset: 0,
+                }),
+                &(SDL_GPUBufferRegion {
+                    buffer: chunk.buffer,
+                    offset: 0,
+                    size: chunk.width * chunk.height * (size_of::<u32>() as u32),
+                }),
+                true
+            );
+            chunk.dirty = false;
+        }
+    }
+}
+
+impl Module for TilemapModule {
+    fn module(world: &World) {
+        world.component::<TileLayer>();
+        world.component::<TilemapPipeline>();
+
+        observer!("init_tilemap_shaders", world, ShadersInitEvent, flecs::Any).each_iter(|it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+            let gpu_device = event.gpu_device;
+
+            // Tile layers are skipped while there is no `TilemapPipeline`
+            let vertex_shader = match load_shader(gpu_device, "tilemap.vert", 0, 1, 1, 0) {
+                Ok(shader) => shader,
+                Err(error) => {
+                    println!("Tilemaps disabled: {}", error);
+                    return;
+                }
+            };
+            let fragment_shader = match load_shader(gpu_device, "tilemap.frag", 1, 1, 0, 0) {
+                Ok(shader) => shader,
+                Err(error) => {
+                    unsafe {
+                        SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                    }
+                    println!("Tilemaps disabled: {}", error);
+                    return;
+                }
+            };
+
+            unsafe {
+                let pipeline_create_info = SDL_GPUGraphicsPipelineCreateInfo {
+                    target_info: SDL_GPUGraphicsPipelineTargetInfo {
+                        num_color_targets: 1,
+                        color_target_descriptions: &(SDL_GPUColorTargetDescription {
+                            format: SDL_GetGPUSwapchainTextureFormat(gpu_device, event.window),
+                            ..Default::default()
+                        }),
+                        depth_stencil_format: DEPTH_FORMAT,
+                        has_depth_stencil_target: true,
+                        ..Default::default()
+                    },
+                    depth_stencil_state: SDL_GPUDepthStencilState {
+                        compare_op: SDL_GPU_COMPAREOP_LESS_OR_EQUAL,
+                        enable_depth_test: true,
+                        enable_depth_write: true,
+                        ..Default::default()
+                    },
+                    multisample_state: SDL_GPUMultisampleState {
+                        sample_count: event.sample_count,
+                        ..Default::default()
+                    },
+                    primitive_type: SDL_GPU_PRIMITIVETYPE_TRIANGLELIST,
+                    vertex_shader,
+                    fragment_shader,
+                    ..Default::default()
+                };
+
+                let pipeline = SDL_CreateGPUGraphicsPipeline(gpu_device, &pipeline_create_info);
+                if pipeline == null_mut() {
+                    panic!("Failed to create Tilemap pipeline");
+                }
+
+                SDL_ReleaseGPUShader(gpu_device, vertex_shader);
+                SDL_ReleaseGPUShader(gpu_device, fragment_shader);
+
+                let sampler = SDL_CreateGPUSampler(
+                    gpu_device,
+                    &(SDL_GPUSamplerCreateInfo {
+                        min_filter: SDL_GPU_FILTER_NEAREST,
+                        mag_filter: SDL_GPU_FILTER_NEAREST,
+                        mipmap_mode: SDL_GPU_SAMPLERMIPMAPMODE_NEAREST,
+                        address_mode_u: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                        address_mode_v: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                        address_mode_w: SDL_GPU_SAMPLERADDRESSMODE_CLAMP_TO_EDGE,
+                        ..Default::default()
+                    })
+                );
+
+                world.set(TilemapPipeline { pipeline, sampler });
+
+                println!("Setting Tilemap Pipeline");
+            }
+        });
+
+        let layers_query = world
+            .query::<(&TileLayer, Option<&RenderLayers>)>()
+            .set_cached()
+            .build();
+
+        system!("tilemap_upload", world, &GpuApi($), &GpuFrame($), &mut TileLayer)
+            .kind::<PreStore>()
+            .each(|(gpu_api, frame, layer)| unsafe {
+                if frame.command_buffer == null_mut() || !layer.chunks.iter().any(|chunk| chunk.dirty) {
+                    return;
+                }
+
+                let copy_pass = SDL_BeginGPUCopyPass(frame.command_buffer);
+                layer.upload(gpu_api.gpu_device, copy_pass);
+                SDL_EndGPUCopyPass(copy_pass);
+            });
+
+        observer!("tilemap_render", world, RenderEvent, flecs::Any).each_iter(move |it, _, _| {
+            let event = &*it.param();
+            let world = it.world();
+
+            world.get::<Option<&TilemapPipeline>>(|pipeline| unsafe {
+                let Some(pipeline) = pipeline else {
+                    return;
+                };
+                let render_pass = event.render_pass;
+                let rect = visible_rect(event.view_projection);
+                let mut bound = false;
+
+                layers_query.each(|(layer, render_layers)| {
+                    if (render_layers.copied().unwrap_or_default().0 & event.layer_mask) == 0 {
+                        return;
+                    }
+
+                    let (tile_uv, uv_offset) = layer.tileset.uv_layout();
+                    for chunk in layer.chunks.iter() {
+                        if chunk.buffer == null_mut() || chunk.tile_count == 0 {
+                            continue;
+                        }
+
+                        let origin =
+                            layer.position.truncate() +
+                            Vec2::new(chunk.origin.0 as f32, chunk.origin.1 as f32) * layer.cell_size;
+                        if let Some((min, max)) = rect {
+                            let end =
+                                origin + Vec2::new(chunk.width as f32, chunk.height as f32) * layer.cell_size;
+                            if origin.cmpgt(max).any() || end.cmplt(min).any() {
+                                continue;
+                            }
+                        }
+
+                        if !bound {
+                            SDL_BindGPUGraphicsPipeline(render_pass, pipeline.pipeline);
+                            bound = true;
+                        }
+
+                        let uniforms = TileVertexUniforms {
+                            view_projection: event.view_projection,
+                            origin: Vec4::new(origin.x, origin.y, layer.position.z, chunk.width as f32),
+                            tile_size: Vec4::new(
+                                layer.cell_size.x,
+                                layer.cell_size.y,
+                                layer.tileset.columns as f32,
+                                0.0
+                            ),
+                            tile_uv,
+                            uv_offset,
+                        };
+
+                        SDL_BindGPUVertexStorageBuffers(render_pass, 0, &chunk.buffer, 1);
+                        SDL_BindGPUFragmentSamplers(
+                            render_pass,
+                            0,
+                            &(SDL_GPUTextureSamplerBinding {
+                                texture: layer.tileset.texture,
+                                sampler: pipeline.sampler,
+                            }),
+                            1
+                        );
+                        SDL_PushGPUVertexUniformData(
+                            event.command_buffer,
+                            0,
+                            &uniforms as *const _ as *const c_void,
+                            size_of::<TileVertexUniforms>() as u32
+                        );
+                        SDL_PushGPUFragmentUniformData(
+                            event.command_buffer,
+                            0,
+                            &layer.color as *const _ as *const c_void,
+                            size_of::<Vec4>() as u32
+                        );
+                        SDL_DrawGPUPrimitives(render_pass, chunk.width * chunk.height * 6, 1, 0, 0);
+                    }
+                });
+            });
+        });
+    }
+}
@@ -0,0 +1,134 @@
+use flecs_ecs::{
+    core::{ flecs, Entity, World, WorldGet },
+    macros::Component,
+    prelude::{ Builder, Module, QueryBuilderImpl },
+};
+
+/// Frame timing through the `Time` and `FixedTime` singletons. The main loop calls `run_frame`
+/// instead of `world.progress()` so `FixedUpdate` systems can run a fixed number of times per
+/// frame. Gameplay systems should move things by `Time::delta` (or `FixedTime::step` in
+/// `FixedUpdate`) so speed does not depend on the frame rate.
+#[derive(Component)]
+pub struct TimeModule;
+
+/// Phase for systems that run at a fixed rate, `.kind::<FixedUpdate>()`. They run before the
+/// regular pipeline each frame, zero or more times, and see the input of the previous frame.
+#[derive(Component)]
+pub struct FixedUpdate;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Time {
+    /// Seconds since the previous frame with `time_scale` applied, zero while paused.
+    pub delta: f32,
+    pub unscaled_delta: f32,
+    /// Sum of `delta`.
+    pub elapsed: f64,
+    pub unscaled_elapsed: f64,
+    pub frame_count: u64,
+    pub time_scale: f32,
+    pub paused: bool,
+    /// Longer frames (breakpoints, window drags) are clamped to this to avoid huge steps.
+    pub max_delta: f32,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FixedTime {
+    /// Seconds simulated by each `FixedUpdate` run.
+    pub step: f32,
+    /// Scaled time not simulated yet, always less than `step` after a frame.
+    pub accumulator: f32,
+    /// `accumulator / step`, for interpolating between the last two fixed states when drawing.
+    pub alpha: f32,
+    /// Upper bound of runs per frame, the remaining time is dropped when a frame takes longer.
+    pub max_steps: u32,
+    /// Runs since startup.
+    pub step_count: u64,
+    pipeline: Entity,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            delta: 0.0,
+            unscaled_delta: 0.0,
+            elapsed: 0.0,
+            unscaled_elapsed: 0.0,
+            frame_count: 0,
+            time_scale: 1.0,
+            paused: false,
+            max_delta: 0.25,
+        }
+    }
+}
+
+impl Time {
+    fn advance(&mut self, delta_time: f32) {
+        self.unscaled_delta = delta_time.clamp(0.0, self.max_delta);
+        self.delta = if self.paused { 0.0 } else { self.unscaled_delta * self.time_scale };
+        self.elapsed += self.delta as f64;
+        self.unscaled_elapsed += self.unscaled_delta as f64;
+        self.frame_count += 1;
+    }
+}
+
+impl FixedTime {
+    /// `rate` is the number of fixed updates per second.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.step = 1.0 / rate;
+    }
+}
+
+/// Advances `Time` by `delta_time` seconds, runs the `FixedUpdate` systems as often as the
+/// accumulated time allows and then the regular pipeline.
+pub fn run_frame(world: &World, delta_time: f32) -> bool {
+    let mut steps = 0;
+    let mut step = 0.0;
+    let mut pipeline = None;
+
+    world.get::<(&mut Time, &mut FixedTime)>(|(time, fixed_time)| {
+        time.advance(delta_time);
+
+        fixed_time.accumulator += time.delta;
+        while fixed_time.accumulator >= fixed_time.step && steps < fixed_time.max_steps {
+            fixed_time.accumulator -= fixed_time.step;
+            steps += 1;
+        }
+        if steps == fixed_time.max_steps {
+            fixed_time.accumulator = fixed_time.accumulator.min(fixed_time.step);
+        }
+
+        fixed_time.alpha = fixed_time.accumulator / fixed_time.step;
+        fixed_time.step_count += steps as u64;
+        step = fixed_time.step;
+        pipeline = Some(fixed_time.pipeline);
+    });
+
+    if let Some(pipeline) = pipeline {
+        for _ in 0..steps {
+            world.run_pipeline_time(pipeline, step);
+        }
+    }
+
+    world.progress_time(delta_time)
+}
+
+impl Module for TimeModule {
+    fn module(world: &World) {
+        world.component::<Time>();
+        world.component::<FixedTime>();
+        world.component::<FixedUpdate>();
+
+        // `FixedUpdate` is not a phase of the default pipeline, its systems only run here
+        let pipeline = world.pipeline().with::<flecs::system::System>().with::<FixedUpdate>().build();
+
+        world.set(Time::default());
+        world.set(FixedTime {
+            step: 1.0 / 60.0,
+            accumulator: 0.0,
+            alpha: 0.0,
+            max_steps: 8,
+            step_count: 0,
+            pipeline: pipeline.entity().id(),
+        });
+    }
+}
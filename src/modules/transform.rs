@@ -0,0 +1,109 @@
+use flecs_ecs::{
+    core::{ flecs::{ self, pipeline::PostUpdate }, TermBuilderImpl, World },
+    macros::{ system, Component },
+    prelude::{ Module, QueryBuilderImpl, SystemAPI },
+};
+use glam::Vec2;
+
+use crate::modules::sprites::Sprite;
+
+/// Propagates `Transform2D` along `ChildOf` into `GlobalTransform2D` at `PostUpdate` and moves
+/// the `Sprite` of every entity that has both to it, so children follow their parents.
+///
+/// Must be imported before `Camera2DModule` so cameras follow this frame's sprite positions.
+#[derive(Component)]
+pub struct TransformModule;
+
+/// Position relative to the parent entity, or to the world without one. Adding it also adds a
+/// `GlobalTransform2D`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Transform2D {
+    pub translation: Vec2,
+    /// Counter-clockwise, in radians.
+    pub rotation: f32,
+    /// Scales the translation of the children, the sprite size stays `Sprite::scale`.
+    pub scale: Vec2,
+}
+
+/// World transform computed from the `Transform2D` chain, read it instead of writing it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GlobalTransform2D {
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self {
+            translation: Vec2::ZERO,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+impl Transform2D {
+    pub fn from_translation(translation: Vec2) -> Self {
+        Self {
+            translation,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for GlobalTransform2D {
+    fn default() -> Self {
+        Self {
+            translation: Vec2::ZERO,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+impl GlobalTransform2D {
+    /// Places `local` inside this transform.
+    pub fn mul_transform(&self, local: &Transform2D) -> Self {
+        Self {
+            translation: self.translation +
+            Vec2::from_angle(self.rotation).rotate(local.translation * self.scale),
+            rotation: self.rotation + local.rotation,
+            scale: self.scale * local.scale,
+        }
+    }
+
+    /// Converts a point from this transform's local space to world space.
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        self.translation + Vec2::from_angle(self.rotation).rotate(point * self.scale)
+    }
+}
+
+impl Module for TransformModule {
+    fn module(world: &World) {
+        world.component::<GlobalTransform2D>();
+        // Entities only propagate with a `GlobalTransform2D` to write to
+        world.component::<Transform2D>().add_trait::<(flecs::With, GlobalTransform2D)>();
+
+        // Parents are visited before their children, so theirs is already up to date
+        world
+            .system_named::<(&Transform2D, Option<&GlobalTransform2D>, &mut GlobalTransform2D)>(
+                "propagate_transform2d"
+            )
+            .term_at(1)
+            .parent()
+            .cascade()
+            .kind::<PostUpdate>()
+            .each(|(transform, parent, global)| {
+                *global = parent.copied().unwrap_or_default().mul_transform(transform);
+            });
+
+        system!("sync_sprite_transforms", world, &GlobalTransform2D, &mut Sprite)
+            .kind::<PostUpdate>()
+            .each(|(global, sprite)| {
+                sprite.position.x = global.translation.x;
+                sprite.position.y = global.translation.y;
+                sprite.rotation = global.rotation;
+            });
+    }
+}
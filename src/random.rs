@@ -0,0 +1,33 @@
+use flecs_ecs::macros::Component;
+use sdl3_sys::{
+    stdinc::{SDL_rand_r, SDL_randf_r},
+    timer::SDL_GetPerformanceCounter,
+};
+
+/// Seedable random numbers, the same seed always produces the same sequence. Gameplay code
+/// should use this singleton instead of `SDL_rand` so replays stay deterministic.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct Random {
+    pub seed: u64,
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    pub fn from_time() -> Self {
+        Self::new(unsafe { SDL_GetPerformanceCounter() })
+    }
+
+    /// A number in `0..n`.
+    pub fn range(&mut self, n: i32) -> i32 {
+        unsafe { SDL_rand_r(&mut self.state, n) }
+    }
+
+    /// A number in `0.0..1.0`.
+    pub fn float(&mut self) -> f32 {
+        unsafe { SDL_randf_r(&mut self.state) }
+    }
+}
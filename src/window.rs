@@ -6,6 +6,24 @@ use sdl3_sys::{self as sdl3, properties::*, video::*};
 #[derive(Debug, Component)]
 pub struct Window(pub *mut SDL_Window);
 
+/// Current size of the window, kept up to date from `WindowResizedEvent`.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct WindowSize {
+    /// Size in screen coordinates.
+    pub width: i32,
+    pub height: i32,
+    /// Size in pixels, which is what the swapchain and viewports use.
+    pub pixel_width: i32,
+    pub pixel_height: i32,
+    pub display_scale: f32,
+}
+
+/// Emitted when the window pixel size or its display scale changed.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct WindowResizedEvent {
+    pub size: WindowSize,
+}
+
 unsafe impl Send for Window {}
 unsafe impl Sync for Window {}
 
@@ -41,4 +59,22 @@ impl Window {
             Self(window)
         }
     }
+
+    pub fn size(&self) -> WindowSize {
+        let mut size = WindowSize {
+            width: 0,
+            height: 0,
+            pixel_width: 0,
+            pixel_height: 0,
+            display_scale: 1.0,
+        };
+
+        unsafe {
+            SDL_GetWindowSize(self.0, &mut size.width, &mut size.height);
+            SDL_GetWindowSizeInPixels(self.0, &mut size.pixel_width, &mut size.pixel_height);
+            size.display_scale = SDL_GetWindowDisplayScale(self.0);
+        }
+
+        size
+    }
 }